@@ -0,0 +1,255 @@
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+
+use crate::create_objects::{BallState, BrickState, PlayerRectangleState, PlayingField};
+use crate::game_logic::{GameState, PaddleDeflection};
+
+pub struct AutopilotPlugin;
+
+#[derive(Resource)]
+pub struct Autopilot {
+    pub enabled: bool,
+    // steer the bounce towards the column with the most remaining hits
+    pub aim_at_bricks: bool,
+    pub paddle_speed: f32,
+}
+
+impl Default for Autopilot {
+    fn default() -> Self {
+        Autopilot {
+            // BRICK_BREAKER_AUTOPILOT=1 starts with the bot in control, e.g. for soak tests
+            enabled: std::env::var("BRICK_BREAKER_AUTOPILOT").is_ok_and(|value| value == "1"),
+            aim_at_bricks: true,
+            paddle_speed: 15.0,
+        }
+    }
+}
+
+impl Autopilot {
+    // the bot also plays the attract mode demo behind the start screen,
+    // it never plays a networked game, the other side couldn't follow it
+    pub fn in_control(&self, game_state: &GameState) -> bool {
+        (self.enabled || game_state.demo) && !game_state.online
+    }
+
+    // the player whose paddle the bot drives, the first one still in the game,
+    // the other paddles stay with their players
+    pub fn driven_player(&self, game_state: &GameState, players: impl Iterator<Item = usize>) -> Option<usize> {
+        if !self.in_control(game_state) {
+            return None;
+        }
+        players.min()
+    }
+}
+
+impl Plugin for AutopilotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (toggle_autopilot, drive_player_rectangle).chain())
+            .init_resource::<Autopilot>();
+    }
+}
+
+// reflect a coordinate into [min, max] as if it bounced off walls at both ends
+fn fold_into_range(value: f32, min: f32, max: f32) -> f32 {
+    let span = max - min;
+    if span <= 0.0 {
+        return min;
+    }
+    let offset = (value - min).rem_euclid(2.0 * span);
+    if offset <= span {
+        min + offset
+    } else {
+        max - (offset - span)
+    }
+}
+
+// x position where the ball centre will cross target_y, bouncing off the side walls
+// and, if the ball is still moving up, off the ceiling once
+pub fn predict_landing_x(
+    position: Vec2,
+    velocity: Vec2,
+    target_y: f32,
+    half_width: f32,
+    half_height: f32,
+    radius: f32,
+) -> Option<f32> {
+    if velocity.y == 0.0 {
+        return None;
+    }
+    let vertical_distance = if velocity.y < 0.0 {
+        position.y - target_y
+    } else {
+        let ceiling = half_height - radius;
+        (ceiling - position.y) + (ceiling - target_y)
+    };
+    if vertical_distance < 0.0 {
+        return None;
+    }
+    let time = vertical_distance / velocity.y.abs();
+    let raw_x = position.x + velocity.x * time;
+    Some(fold_into_range(raw_x, -half_width + radius, half_width - radius))
+}
+
+// offset of the ball from the paddle centre that makes deflect() send the ball
+// from landing_x towards the target, ignoring the paddle's own movement
+pub fn aim_offset(
+    landing_x: f32,
+    landing_y: f32,
+    target: Vec2,
+    rectangle_width: f32,
+    deflection: &PaddleDeflection,
+) -> f32 {
+    let vertical_distance = target.y - landing_y;
+    if vertical_distance <= 0.0 || deflection.max_bounce_angle <= 0.0 {
+        return 0.0;
+    }
+    let angle = (target.x - landing_x).atan2(vertical_distance);
+    let offset = angle / deflection.max_bounce_angle / 2.0;
+    // the ball can only touch the paddle within half its width
+    offset.clamp(-0.45, 0.45) * rectangle_width
+}
+
+// centre of the brick column with the highest remaining hit_bar sum
+fn densest_brick_column(bricks: &Query<(&GlobalTransform, &BrickState)>) -> Option<Vec2> {
+    let mut columns: Vec<(Vec2, f32, i32)> = Vec::new();
+    for (global_transform, brick_state) in bricks {
+        if brick_state.hit_bar <= 0 {
+            continue;
+        }
+        let position = global_transform.translation().truncate();
+        match columns
+            .iter_mut()
+            .find(|(column, _, _)| (column.x - position.x).abs() < brick_state.width / 2.0)
+        {
+            Some((column, lowest_y, hits)) => {
+                *hits += brick_state.hit_bar;
+                if position.y < *lowest_y {
+                    *lowest_y = position.y;
+                    column.y = position.y;
+                }
+            }
+            None => columns.push((position, position.y, brick_state.hit_bar)),
+        }
+    }
+    columns
+        .into_iter()
+        .max_by_key(|(_, _, hits)| *hits)
+        .map(|(column, _, _)| column)
+}
+
+fn toggle_autopilot(keyboard_input: Res<ButtonInput<KeyCode>>, mut autopilot: ResMut<Autopilot>) {
+    if keyboard_input.just_pressed(KeyCode::KeyB) {
+        autopilot.enabled = !autopilot.enabled;
+    }
+}
+
+fn drive_player_rectangle(
+    autopilot: Res<Autopilot>,
+    game_state: Res<GameState>,
+    mut ball_query: Query<(&mut BallState, &mut LinearVelocity, &Transform), Without<PlayerRectangleState>>,
+    mut rectangle_query: Query<(&mut Transform, &PlayerRectangleState), Without<BallState>>,
+    brick_query: Query<(&GlobalTransform, &BrickState)>,
+    field: Res<PlayingField>,
+    deflection: Res<PaddleDeflection>,
+) {
+    if !autopilot.in_control(&game_state) || !game_state.in_game || game_state.paused {
+        return;
+    }
+    let (mut ball_state, mut linear_velocity, ball_transform) = ball_query.single_mut();
+    // the bot plays the first player, whose paddle serves
+    let player = autopilot.driven_player(&game_state, rectangle_query.iter().map(|(_, rectangle_state)| rectangle_state.player));
+    let Some((mut rectangle_transform, rectangle_state)) = rectangle_query
+        .iter_mut()
+        .find(|(_, rectangle_state)| Some(rectangle_state.player) == player)
+    else {
+        return;
+    };
+    let half_width = field.width / 2.0;
+
+    if !ball_state.active {
+        ball_state.active = true;
+        linear_velocity.x = 0.0;
+        linear_velocity.y = ball_state.speed;
+        return;
+    }
+
+    let paddle_y = rectangle_transform.translation.y + rectangle_state.height / 2.0 + ball_state.radius;
+    let Some(landing_x) = predict_landing_x(
+        ball_transform.translation.truncate(),
+        linear_velocity.0,
+        paddle_y,
+        half_width,
+        field.height / 2.0,
+        ball_state.radius,
+    ) else {
+        return;
+    };
+
+    let mut target_x = landing_x;
+    if autopilot.aim_at_bricks && linear_velocity.y < 0.0 {
+        if let Some(column) = densest_brick_column(&brick_query) {
+            target_x -= aim_offset(landing_x, paddle_y, column, rectangle_state.width, &deflection);
+        }
+    }
+
+    let target_x = target_x.clamp(rectangle_state.min_x, rectangle_state.max_x);
+    let step = (target_x - rectangle_transform.translation.x)
+        .clamp(-autopilot.paddle_speed, autopilot.paddle_speed);
+    rectangle_transform.translation.x += step;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HALF_WIDTH: f32 = 300.0;
+    const HALF_HEIGHT: f32 = 200.0;
+    const RADIUS: f32 = 10.0;
+
+    fn landing_x(velocity: Vec2) -> Option<f32> {
+        predict_landing_x(Vec2::new(0.0, 100.0), velocity, 0.0, HALF_WIDTH, HALF_HEIGHT, RADIUS)
+    }
+
+    #[test]
+    fn fold_into_range_bounces_off_both_ends() {
+        assert_eq!(fold_into_range(3.0, -10.0, 10.0), 3.0);
+        assert_eq!(fold_into_range(12.0, -10.0, 10.0), 8.0);
+        assert_eq!(fold_into_range(-13.0, -10.0, 10.0), -7.0);
+        // off the right wall, the left one and back towards the right
+        assert_eq!(fold_into_range(35.0, -10.0, 10.0), -5.0);
+        assert_eq!(fold_into_range(55.0, -10.0, 10.0), 5.0);
+        assert_eq!(fold_into_range(4.0, 2.0, 2.0), 2.0);
+    }
+
+    #[test]
+    fn predict_landing_x_folds_off_the_walls() {
+        // the walls sit a radius further out than the ball centre gets
+        assert_eq!(landing_x(Vec2::new(100.0, -100.0)), Some(100.0));
+        assert_eq!(landing_x(Vec2::new(400.0, -100.0)), Some(180.0));
+        assert_eq!(landing_x(Vec2::new(1500.0, -100.0)), Some(240.0));
+        assert_eq!(landing_x(Vec2::new(-1500.0, -100.0)), Some(-240.0));
+    }
+
+    #[test]
+    fn predict_landing_x_goes_via_the_ceiling() {
+        // 90 up to the ceiling and 190 down to the paddle
+        assert_eq!(landing_x(Vec2::new(100.0, 100.0)), Some(280.0));
+        assert_eq!(landing_x(Vec2::new(100.0, 0.0)), None);
+        // already past the paddle
+        let below = predict_landing_x(Vec2::new(0.0, -50.0), Vec2::new(0.0, -100.0), 0.0, HALF_WIDTH, HALF_HEIGHT, RADIUS);
+        assert_eq!(below, None);
+    }
+
+    #[test]
+    fn aim_offset_points_the_bounce_at_the_target() {
+        let deflection = PaddleDeflection::default();
+        let width = 100.0;
+        assert_eq!(aim_offset(0.0, 0.0, Vec2::new(0.0, 200.0), width, &deflection), 0.0);
+        let right = aim_offset(0.0, 0.0, Vec2::new(50.0, 200.0), width, &deflection);
+        let left = aim_offset(0.0, 0.0, Vec2::new(-50.0, 200.0), width, &deflection);
+        assert!(right > 0.0 && (right + left).abs() < 0.001);
+        // never further out than the paddle can touch the ball
+        assert_eq!(aim_offset(0.0, 0.0, Vec2::new(10_000.0, 1.0), width, &deflection), 0.45 * width);
+        assert_eq!(aim_offset(0.0, 0.0, Vec2::new(50.0, -10.0), width, &deflection), 0.0);
+    }
+}
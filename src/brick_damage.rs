@@ -0,0 +1,149 @@
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+    utils::HashMap,
+};
+use std::time::Duration;
+
+use crate::create_objects::{BrickState, BrickVisual};
+use crate::theme::Theme;
+
+// shared brick materials and the flash and squash played when a brick is hit
+pub struct BrickDamagePlugin;
+
+const FLASH_SECS: f32 = 0.06;
+const SQUASH_SECS: f32 = 0.15;
+// how much of its height a brick loses at the peak of the squash
+const SQUASH_AMOUNT: f32 = 0.2;
+
+// one material per (max_hit_bar, hit_bar), shared by every brick in that state
+// so hits don't add a new material to the asset store each time
+#[derive(Resource, Default)]
+pub struct BrickMaterials {
+    tiers: HashMap<(i32, i32), Handle<ColorMaterial>>,
+}
+
+impl BrickMaterials {
+    pub fn get(
+        &mut self,
+        brick_state: &BrickState,
+        theme: &Theme,
+        materials: &mut Assets<ColorMaterial>,
+    ) -> Handle<ColorMaterial> {
+        self.tiers
+            .entry((brick_state.max_hit_bar, brick_state.hit_bar))
+            .or_insert_with(|| materials.add(theme.brick(brick_state.hit_bar)))
+            .clone()
+    }
+}
+
+// added to a brick that was hit and survived, removed once the animation is over
+#[derive(Component)]
+pub struct BrickFlash {
+    timer: Timer,
+}
+
+impl Default for BrickFlash {
+    fn default() -> Self {
+        BrickFlash {
+            timer: Timer::new(Duration::from_secs_f32(SQUASH_SECS), TimerMode::Once),
+        }
+    }
+}
+
+#[derive(Resource)]
+struct FlashAssets {
+    material: Handle<ColorMaterial>,
+}
+
+#[derive(Component)]
+struct FlashOverlay;
+
+impl Plugin for BrickDamagePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BrickMaterials>()
+            .add_systems(Startup, setup)
+            .add_systems(Update, (recolor_brick_materials, add_flash_overlays, animate_hits));
+    }
+}
+
+fn setup(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    commands.insert_resource(FlashAssets {
+        material: materials.add(Color::WHITE),
+    });
+}
+
+// materials that no brick uses right now are missed by the theme's own recoloring
+fn recolor_brick_materials(
+    theme: Res<Theme>,
+    brick_materials: Res<BrickMaterials>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if !theme.is_changed() {
+        return;
+    }
+    for (&(_, hit_bar), handle) in &brick_materials.tiers {
+        if let Some(material) = materials.get_mut(handle) {
+            material.color = theme.brick(hit_bar);
+        }
+    }
+}
+
+fn add_flash_overlays(
+    mut commands: Commands,
+    flash_assets: Res<FlashAssets>,
+    visual_query: Query<(Entity, &Mesh2dHandle), Added<BrickVisual>>,
+) {
+    // on the visual, so the overlay squashes along with the brick
+    for (entity, mesh) in &visual_query {
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                MaterialMesh2dBundle {
+                    mesh: mesh.clone(),
+                    material: flash_assets.material.clone(),
+                    // above the cracks and the health number
+                    transform: Transform::from_xyz(0.0, 0.0, 0.3),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                FlashOverlay,
+            ));
+        });
+    }
+}
+
+fn animate_hits(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut brick_query: Query<(Entity, &mut BrickFlash, &Children)>,
+    // only the visual is scaled, the collider on the brick keeps its size
+    mut visual_query: Query<(&mut Transform, &Children), With<BrickVisual>>,
+    mut overlay_query: Query<&mut Visibility, With<FlashOverlay>>,
+) {
+    for (entity, mut flash, children) in &mut brick_query {
+        flash.timer.tick(time.delta());
+        let elapsed = flash.timer.elapsed_secs();
+        let mut visuals = visual_query.iter_many_mut(children);
+        while let Some((mut transform, visual_children)) = visuals.fetch_next() {
+            for child in visual_children {
+                if let Ok(mut visibility) = overlay_query.get_mut(*child) {
+                    *visibility = if elapsed < FLASH_SECS && !flash.timer.finished() {
+                        Visibility::Inherited
+                    } else {
+                        Visibility::Hidden
+                    };
+                }
+            }
+            // squashes and springs back, the brick mesh is height x width before its rotation
+            let squash = SQUASH_AMOUNT * (flash.timer.fraction() * std::f32::consts::PI).sin();
+            transform.scale = if flash.timer.finished() {
+                Vec3::ONE
+            } else {
+                Vec3::new(1.0 - squash, 1.0 + squash / 2.0, 1.0)
+            };
+        }
+        if flash.timer.finished() {
+            commands.entity(entity).remove::<BrickFlash>();
+        }
+    }
+}
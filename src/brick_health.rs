@@ -0,0 +1,180 @@
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use crate::create_objects::BrickState;
+use crate::theme::Theme;
+
+// shows brick durability without relying on color alone
+pub struct BrickHealthPlugin;
+
+// crack angles relative to the brick's long side, one more crack per hit taken
+const CRACK_ANGLES: [f32; 4] = [0.1, -0.15, 0.3, -0.35];
+
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BrickHealthSettings {
+    // remaining hits written on each brick
+    pub numbers: bool,
+    // dark cracks drawn over damaged bricks
+    pub cracks: bool,
+}
+
+impl Default for BrickHealthSettings {
+    fn default() -> Self {
+        BrickHealthSettings {
+            numbers: false,
+            cracks: true,
+        }
+    }
+}
+
+// the crack mesh of every brick size, shared like BrickMaterials so hits don't add meshes
+#[derive(Resource)]
+struct CrackAssets {
+    material: Handle<ColorMaterial>,
+    meshes: HashMap<(u32, u32), Mesh2dHandle>,
+}
+
+impl CrackAssets {
+    fn mesh(&mut self, brick_state: &BrickState, meshes: &mut Assets<Mesh>) -> Mesh2dHandle {
+        self.meshes
+            .entry((brick_state.width.to_bits(), brick_state.height.to_bits()))
+            .or_insert_with(|| {
+                // the brick mesh is height x width before its rotation
+                let length = brick_state.width * 0.6;
+                let thickness = (brick_state.height / 12.0).max(1.0);
+                Mesh2dHandle(meshes.add(Rectangle::new(thickness, length)))
+            })
+            .clone()
+    }
+}
+
+#[derive(Component)]
+struct BrickHealthLabel;
+
+#[derive(Component)]
+struct BrickCrack;
+
+impl Plugin for BrickHealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup)
+            .add_systems(Update, (label_new_bricks, update_labels, update_cracks).chain())
+            .init_resource::<BrickHealthSettings>();
+    }
+}
+
+fn setup(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    commands.insert_resource(CrackAssets {
+        material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.7)),
+        meshes: HashMap::new(),
+    });
+}
+
+fn label_new_bricks(
+    mut commands: Commands,
+    brick_query: Query<(Entity, &BrickState), Added<BrickState>>,
+    asset_server: Res<AssetServer>,
+    theme: Res<Theme>,
+) {
+    for (entity, brick_state) in &brick_query {
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        brick_state.hit_bar.to_string(),
+                        TextStyle {
+                            font: asset_server.load("embedded://fonts/AgaveNerdFont-Bold.ttf"),
+                            font_size: brick_state.height * 0.8,
+                            color: theme.background.with_a(1.0),
+                        },
+                    ),
+                    // undo the brick's rotation so the number stays upright
+                    transform: Transform {
+                        translation: Vec3::new(0.0, 0.0, 0.2),
+                        rotation: Quat::from_rotation_z(-PI / 2.0),
+                        ..default()
+                    },
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                BrickHealthLabel,
+            ));
+        });
+    }
+}
+
+fn update_labels(
+    settings: Res<BrickHealthSettings>,
+    theme: Res<Theme>,
+    brick_query: Query<(Ref<BrickState>, &Children)>,
+    mut label_query: Query<(&mut Text, &mut Visibility), With<BrickHealthLabel>>,
+) {
+    for (brick_state, children) in &brick_query {
+        if !(brick_state.is_changed() || settings.is_changed() || theme.is_changed()) {
+            continue;
+        }
+        for child in children {
+            if let Ok((mut text, mut visibility)) = label_query.get_mut(*child) {
+                text.sections[0].value = brick_state.hit_bar.to_string();
+                text.sections[0].style.color = theme.background.with_a(1.0);
+                *visibility = if settings.numbers {
+                    Visibility::Inherited
+                } else {
+                    Visibility::Hidden
+                };
+            }
+        }
+    }
+}
+
+fn update_cracks(
+    mut commands: Commands,
+    settings: Res<BrickHealthSettings>,
+    mut crack_assets: ResMut<CrackAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    brick_query: Query<(Entity, Ref<BrickState>, Option<&Children>)>,
+    crack_query: Query<(), With<BrickCrack>>,
+) {
+    for (entity, brick_state, children) in &brick_query {
+        if !(brick_state.is_changed() || settings.is_changed()) {
+            continue;
+        }
+        let existing: Vec<Entity> = children
+            .map(|children| children.iter().copied().filter(|child| crack_query.contains(*child)).collect())
+            .unwrap_or_default();
+        let wanted = if settings.cracks {
+            ((brick_state.max_hit_bar - brick_state.hit_bar).max(0) as usize).min(CRACK_ANGLES.len())
+        } else {
+            0
+        };
+        for crack in existing.iter().skip(wanted) {
+            commands.entity(*crack).despawn_recursive();
+        }
+        if existing.len() >= wanted {
+            continue;
+        }
+        let mesh = crack_assets.mesh(&brick_state, &mut meshes);
+        commands.entity(entity).with_children(|parent| {
+            for angle in &CRACK_ANGLES[existing.len()..wanted] {
+                parent.spawn((
+                    MaterialMesh2dBundle {
+                        mesh: mesh.clone(),
+                        material: crack_assets.material.clone(),
+                        transform: Transform {
+                            translation: Vec3::new(0.0, 0.0, 0.1),
+                            rotation: Quat::from_rotation_z(*angle),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    BrickCrack,
+                ));
+            }
+        });
+    }
+}
@@ -0,0 +1,159 @@
+use bevy::{prelude::*, sprite::Mesh2dHandle};
+use serde::{Deserialize, Serialize};
+
+use crate::create_objects::{PlayerRectangle, PlayerRectangleBundle, PlayerRectangleState, PlayingField};
+use crate::game_logic::{BallLostEvent, GameMode, GameState};
+
+// two players side by side, the first on the right half with the arrow keys,
+// the second on the left half with A and D
+pub struct CoopPlugin;
+
+const PLAYERS: usize = 2;
+
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CoopSettings {
+    // one pool of lives for both players, otherwise each has their own and a
+    // player who runs out leaves the whole width to the other
+    pub shared_lives: bool,
+}
+
+impl Default for CoopSettings {
+    fn default() -> Self {
+        CoopSettings { shared_lives: true }
+    }
+}
+
+impl Plugin for CoopPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CoopSettings>()
+            .add_systems(Update, (lose_player_lives, arrange_paddles).chain());
+    }
+}
+
+fn players_in_game(game_state: &GameState) -> Vec<usize> {
+    if game_state.mode != GameMode::Coop {
+        return vec![0];
+    }
+    (0..PLAYERS)
+        .filter(|player| game_state.rules.shared_lives || game_state.player_lives[*player] > 0)
+        .collect()
+}
+
+// a lost ball costs a life of the player on whose side it fell
+fn lose_player_lives(
+    mut ball_lost_event: EventReader<BallLostEvent>,
+    mut game_state: ResMut<GameState>,
+) {
+    for event in ball_lost_event.read() {
+        if game_state.mode != GameMode::Coop || game_state.rules.shared_lives {
+            continue;
+        }
+        let players = players_in_game(&game_state);
+        let player = match players.as_slice() {
+            [_, _] if event.position.x >= 0.0 => 0,
+            [_, _] => 1,
+            [player] => *player,
+            _ => continue,
+        };
+        game_state.player_lives[player] -= 1;
+    }
+}
+
+// where a player's paddle goes, its centre moves between min_x and max_x
+struct PaddleSlot {
+    player: usize,
+    min_x: f32,
+    max_x: f32,
+    top: bool,
+}
+
+fn paddle_slots(game_state: &GameState, half_width: f32, width: f32) -> Vec<PaddleSlot> {
+    let full = (-half_width + width / 2.0, half_width - width / 2.0);
+    if game_state.mode == GameMode::Versus {
+        // the first player at the bottom, the second at the top, both across the whole width
+        return (0..PLAYERS)
+            .map(|player| PaddleSlot { player, min_x: full.0, max_x: full.1, top: player == 1 })
+            .collect();
+    }
+    let players = players_in_game(game_state);
+    players
+        .iter()
+        .map(|&player| {
+            // the first player on the right, the second on the left, or the whole width alone
+            let (min_x, max_x) = match (players.len(), player) {
+                (1, _) => full,
+                (_, 0) => (width / 2.0, full.1),
+                _ => (full.0, -width / 2.0),
+            };
+            PaddleSlot { player, min_x, max_x, top: false }
+        })
+        .collect()
+}
+
+// spawns, moves and removes paddles to match the players in the game
+fn arrange_paddles(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    mut rectangle_query: Query<(
+        Entity,
+        &mut Transform,
+        &mut PlayerRectangleState,
+        &Mesh2dHandle,
+        &Handle<ColorMaterial>,
+    )>,
+    field: Res<PlayingField>,
+) {
+    let Some((_, template_transform, template_state, mesh, material)) = rectangle_query.iter().next() else {
+        return;
+    };
+    // the paddles sit as far from the top as from the bottom
+    let bottom_y = -template_transform.translation.y.abs();
+    let (width, height) = (template_state.width, template_state.height);
+    let (mesh, material) = (mesh.clone(), material.clone());
+    let slots = paddle_slots(&game_state, field.width / 2.0, width);
+    // nobody left means the game is over, the paddles stay until the next one
+    if slots.is_empty() {
+        return;
+    }
+    let in_place = rectangle_query.iter().count() == slots.len()
+        && rectangle_query.iter().all(|(_, transform, state, _, _)| {
+            slots.iter().any(|slot| {
+                slot.player == state.player
+                    && slot.min_x == state.min_x
+                    && slot.max_x == state.max_x
+                    && slot.top == (transform.translation.y > 0.0)
+            })
+        });
+    if in_place {
+        return;
+    }
+    let slot_y = |slot: &PaddleSlot| if slot.top { -bottom_y } else { bottom_y };
+    let mut present = Vec::new();
+    for (entity, mut transform, mut state, _, _) in &mut rectangle_query {
+        let Some(slot) = slots.iter().find(|slot| slot.player == state.player) else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+        (state.min_x, state.max_x) = (slot.min_x, slot.max_x);
+        transform.translation.x = (slot.min_x + slot.max_x) / 2.0;
+        transform.translation.y = slot_y(slot);
+        present.push(state.player);
+    }
+    for slot in slots.iter().filter(|slot| !present.contains(&slot.player)) {
+        let state = PlayerRectangleState {
+            width,
+            height,
+            velocity: 0.0,
+            previous_x: 0.0,
+            player: slot.player,
+            min_x: slot.min_x,
+            max_x: slot.max_x,
+        };
+        let position = Vec2::new((slot.min_x + slot.max_x) / 2.0, slot_y(slot));
+        commands.spawn((
+            PlayerRectangleBundle::new(state, mesh.clone(), material.clone(), position),
+            PlayerRectangle,
+        ));
+    }
+}
@@ -1,29 +1,65 @@
-use crate::game_logic::RecreateBricksEvent;
+use crate::brick_damage::BrickMaterials;
+use crate::game_logic::{DifficultySettings, GameState, RecreateBricksEvent};
+use crate::level::{Level, LevelGrid};
+use crate::level_generator::current_layout;
+use crate::screen_effects::CameraShake;
+use crate::theme::Theme;
+use crate::trail::BallTrail;
 use bevy::{
     prelude::*,
+    render::{camera::ScalingMode, mesh::VertexAttributeValues},
     sprite::{MaterialMesh2dBundle, Mesh2dHandle},
 };
 use bevy_xpbd_2d::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::f32::consts::PI;
 use std::time::Duration;
 
 pub struct CreateObjectsPlugin;
 
+// the size of the playing field in world units, the same whatever the window is,
+// the camera scales the field to fit the window
+#[derive(Resource, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PlayingField {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for PlayingField {
+    fn default() -> Self {
+        // the size of the default window
+        PlayingField {
+            width: 1280.0,
+            height: 720.0,
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct BallState {
     pub radius: f32,
     pub speed: f32,
-    pub direction: Vec3,
     pub active: bool,
     pub initial_position: Vec3,
+    // time since the ball last touched the paddle or a brick
+    pub contact_timer: Timer,
+    // whose paddle the ball last bounced off, bricks it breaks score for them
+    pub last_player: usize,
+    // whose paddle the ball waits on before it is launched
+    pub server: usize,
+    // the side the ball last moved towards, 1.0 or -1.0, a ball going straight up or down
+    // is turned that way
+    pub drift: f32,
 }
 
 #[derive(Component)]
 pub struct Walls;
 
+// goal line, the ball is lost when it crosses the line of a player in the game
 #[derive(Component)]
 pub struct Floor {
-    pub hit_timer: Timer,
+    // the player defending the line, the top one only has a player in versus games
+    pub player: usize,
 }
 
 #[derive(Component)]
@@ -31,13 +67,24 @@ pub struct BrickState {
     pub width: f32,
     pub height: f32,
     pub hit_bar: i32,
-    pub hit_timer: Timer,
+    // hit_bar when the brick was created
+    pub max_hit_bar: i32,
+    // 0 is the lowest row of the layout
+    pub row: i32,
 }
 
 #[derive(Component)]
 pub struct PlayerRectangleState {
     pub width: f32,
     pub height: f32,
+    // horizontal speed in units per second, measured from the last frame's movement
+    pub velocity: f32,
+    pub previous_x: f32,
+    // 0 for the first player, who also plays alone
+    pub player: usize,
+    // the part of the screen the paddle's centre may move in
+    pub min_x: f32,
+    pub max_x: f32,
 }
 
 #[derive(Bundle)]
@@ -46,10 +93,50 @@ pub struct BrickBundle {
     restitution: Restitution,
     body: RigidBody,
     collider: Collider,
-    material_mesh: MaterialMesh2dBundle<ColorMaterial>,
+    spatial: SpatialBundle,
     state: BrickState,
 }
 
+impl BrickBundle {
+    pub fn new(state: BrickState, position: Vec2) -> Self {
+        BrickBundle {
+            friction: Friction::new(0.0),
+            restitution: Restitution::new(1.0),
+            body: RigidBody::Static,
+            collider: Collider::rectangle(state.height, state.width),
+            spatial: SpatialBundle::from_transform(Transform {
+                rotation: Quat::from_rotation_z(PI / 2.0),
+                translation: position.extend(0.0),
+                ..default()
+            }),
+            state,
+        }
+    }
+}
+
+// the mesh of a brick lives on a child, so animating its scale leaves the collider alone
+#[derive(Component)]
+pub struct BrickVisual;
+
+#[derive(Bundle)]
+pub struct BrickVisualBundle {
+    material_mesh: MaterialMesh2dBundle<ColorMaterial>,
+    visual: BrickVisual,
+}
+
+impl BrickVisualBundle {
+    pub fn new(mesh: Mesh2dHandle, material: Handle<ColorMaterial>) -> Self {
+        BrickVisualBundle {
+            material_mesh: MaterialMesh2dBundle {
+                mesh,
+                material,
+                ..default()
+            },
+            visual: BrickVisual,
+        }
+    }
+}
+
 #[derive(Bundle)]
 pub struct PlayerRectangleBundle {
     friction: Friction,
@@ -60,33 +147,80 @@ pub struct PlayerRectangleBundle {
     state: PlayerRectangleState,
 }
 
+impl PlayerRectangleBundle {
+    pub fn new(state: PlayerRectangleState, mesh: Mesh2dHandle, material: Handle<ColorMaterial>, position: Vec2) -> Self {
+        PlayerRectangleBundle {
+            body: RigidBody::Static,
+            friction: Friction::new(0.0),
+            restitution: Restitution::new(1.0),
+            collider: Collider::rectangle(state.height, state.width),
+            material_mesh: MaterialMesh2dBundle {
+                mesh,
+                material,
+                transform: Transform {
+                    rotation: Quat::from_rotation_z(PI / 2.0),
+                    translation: position.extend(0.0),
+                    scale: Vec3 {
+                        x: 1.0,
+                        y: 1.0,
+                        z: 1.0,
+                    },
+                },
+                ..default()
+            },
+            state,
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct PlayerRectangle;
 
 impl Plugin for CreateObjectsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup)
+        app.init_resource::<PlayingField>()
+            .add_systems(Startup, setup)
             .add_systems(Update, recreate_bricks);
     }
 }
 
+// the meshes below are turned a quarter with their transform, turn the texture
+// coordinates back so textures show upright
+pub fn upright_uvs(mut mesh: Mesh) -> Mesh {
+    if let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute_mut(Mesh::ATTRIBUTE_UV_0) {
+        for uv in uvs.iter_mut() {
+            *uv = [uv[1], 1.0 - uv[0]];
+        }
+    }
+    mesh
+}
+
 fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
-    window: Query<&Window>,
+    field: Res<PlayingField>,
+    theme: Res<Theme>,
+    difficulty: Res<DifficultySettings>,
 ) {
-    commands.spawn(Camera2dBundle::default());
-    let window = window.single();
-    let radius = window.height() / 38.0;
+    let mut camera = Camera2dBundle::default();
+    camera.projection.scaling_mode = ScalingMode::AutoMin {
+        min_width: field.width,
+        min_height: field.height,
+    };
+    commands.spawn((camera, CameraShake::default()));
+    let radius = field.height / 38.0;
     let ball_state = BallState {
         radius,
-        speed: 800.0,
-        direction: Vec3::new(0.0, 1.0, 0.0),
+        speed: difficulty.ball_speed,
         active: false,
-        initial_position: Vec3::new(0.0, -window.height() / 2.0 + radius * 2.0, 0.0),
+        initial_position: Vec3::new(0.0, -field.height / 2.0 + radius * 2.0, 0.0),
+        contact_timer: Timer::new(Duration::from_secs_f32(8.0), TimerMode::Once),
+        last_player: 0,
+        server: 0,
+        drift: 1.0,
     };
-    let ball = Mesh2dHandle(meshes.add(Circle { radius }));
+    let ball = Mesh2dHandle(meshes.add(upright_uvs(Circle { radius }.into())));
     let mut impulse = ExternalImpulse::new(Vec2::new(0.0, 0.0));
     commands.spawn((
         RigidBody::Dynamic,
@@ -99,13 +233,13 @@ fn setup(
         LinearVelocity(Vec2::new(0.0, 0.0)),
         MaterialMesh2dBundle {
             mesh: ball,
-            material: materials.add(Color::rgb(1.0, 0.0, 0.0)),
+            material: materials.add(theme.ball),
             transform: Transform {
                 rotation: Quat::from_rotation_z(PI / 2.0),
                 translation: Vec3 {
                     x: 0.0,
                     // add offset to prevent ball from getting stuck
-                    y: -window.height() / 2.0 + radius * 2.0, // + window.height() / 60.0,
+                    y: -field.height / 2.0 + radius * 2.0, // + field.height / 60.0,
                     z: 0.0,
                 },
                 scale: Vec3 {
@@ -117,53 +251,37 @@ fn setup(
             ..default()
         },
         ball_state,
+        BallTrail::new(theme.ball),
     ));
     impulse.apply_impulse(Vec2::X);
-    let rectangle_width = window.width() / 7.0;
-    let rectangle_height = window.height() / 40.0;
-    let rectangle = Mesh2dHandle(meshes.add(Rectangle::new(rectangle_height, rectangle_width)));
+    let rectangle_width = field.width / 7.0;
+    let rectangle_height = field.height / 40.0;
+    let rectangle = Mesh2dHandle(meshes.add(upright_uvs(Rectangle::new(rectangle_height, rectangle_width).into())));
     let state = PlayerRectangleState {
         width: rectangle_width,
         height: rectangle_height,
+        velocity: 0.0,
+        previous_x: 0.0,
+        player: 0,
+        min_x: -field.width / 2.0 + rectangle_width / 2.0,
+        max_x: field.width / 2.0 - rectangle_width / 2.0,
     };
+    let position = Vec2::new(0.0, -field.height / 2.0 + field.height / 100.0);
     commands.spawn((
-        PlayerRectangleBundle {
-            body: RigidBody::Static,
-            friction: Friction::new(0.0),
-            restitution: Restitution::new(1.0),
-            collider: Collider::rectangle(rectangle_height, rectangle_width),
-            material_mesh: MaterialMesh2dBundle {
-                mesh: rectangle,
-                material: materials.add(Color::rgb(0.0, 0.0, 1.0)),
-                transform: Transform {
-                    rotation: Quat::from_rotation_z(PI / 2.0),
-                    translation: Vec3 {
-                        x: 0.0,
-                        y: -window.height() / 2.0 + window.height() / 100.0,
-                        z: 0.0,
-                    },
-                    scale: Vec3 {
-                        x: 1.0,
-                        y: 1.0,
-                        z: 1.0,
-                    },
-                },
-                ..default()
-            },
-            state,
-        },
+        PlayerRectangleBundle::new(state, rectangle, materials.add(theme.paddle), position),
         PlayerRectangle,
     ));
     commands.spawn((
         RigidBody::Static,
         Collider::polyline(
             vec![
-                Vec2::new(-window.width() / 2.0, -window.height() / 2.0),
-                Vec2::new(-window.width() / 2.0, window.height() / 2.0),
-                Vec2::new(window.width() / 2.0, window.height() / 2.0),
-                Vec2::new(window.width() / 2.0, -window.height() / 2.0),
+                Vec2::new(-field.width / 2.0, -field.height / 2.0),
+                Vec2::new(-field.width / 2.0, field.height / 2.0),
+                Vec2::new(field.width / 2.0, field.height / 2.0),
+                Vec2::new(field.width / 2.0, -field.height / 2.0),
             ],
-            None,
+            // the sides only, the top is a goal line
+            Some(vec![[0, 1], [2, 3]]),
         ),
         Restitution::new(1.0),
         Friction::new(0.0),
@@ -173,75 +291,67 @@ fn setup(
         RigidBody::Static,
         Collider::polyline(
             vec![
-                Vec2::new(-window.width() / 2.0, -window.height() / 2.0),
-                Vec2::new(window.width() / 2.0, -window.height() / 2.0),
+                Vec2::new(-field.width / 2.0, -field.height / 2.0),
+                Vec2::new(field.width / 2.0, -field.height / 2.0),
             ],
             None,
         ),
         Restitution::new(1.0),
         Friction::new(0.0),
-        Floor {
-            hit_timer: Timer::new(Duration::from_secs_f32(10000.0), TimerMode::Repeating),
-        },
+        Floor { player: 0 },
+    ));
+    commands.spawn((
+        RigidBody::Static,
+        Collider::polyline(
+            vec![
+                Vec2::new(-field.width / 2.0, field.height / 2.0),
+                Vec2::new(field.width / 2.0, field.height / 2.0),
+            ],
+            None,
+        ),
+        Restitution::new(1.0),
+        Friction::new(0.0),
+        Floor { player: 1 },
     ));
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn recreate_bricks(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
-    window: Query<&Window>,
+    field: Res<PlayingField>,
     mut recreate_bricks_event: EventReader<RecreateBricksEvent>,
     brick_query: Query<Entity, With<BrickState>>,
+    theme: Res<Theme>,
+    mut brick_materials: ResMut<BrickMaterials>,
+    level: Res<Level>,
+    game_state: Res<GameState>,
 ) {
    for _ in recreate_bricks_event.read() {
         for entity in &brick_query {
-            commands.entity(entity).despawn();
+            commands.entity(entity).despawn_recursive();
         }
-        for i in -2..3 {
-            for j in -2..3 {
-                let window = window.single();
-                let rectangle_width = window.width() / 8.;
-                let rectangle_height = window.height() / 20.;
-                let rectangle =
-                    Mesh2dHandle(meshes.add(Rectangle::new(rectangle_height, rectangle_width)));
-                let state = BrickState {
-                    width: rectangle_width,
-                    height: rectangle_height,
-                    hit_timer: Timer::new(Duration::from_secs_f32(1000.0), TimerMode::Repeating),
-                    hit_bar: if (j % 2 == 0 && (i == 1 || i == -1)) || (j == 0 && i == 0) {
-                        3
-                    } else {
-                        1
-                    },
-                };
-                commands.spawn(BrickBundle {
-                    friction: Friction::new(0.0),
-                    restitution: Restitution::new(1.0),
-                    body: RigidBody::Static,
-                    collider: Collider::rectangle(rectangle_height, rectangle_width),
-                    material_mesh: MaterialMesh2dBundle {
-                        mesh: rectangle,
-                        material: materials.add(Color::rgb(0.0, 1.0 / state.hit_bar as f32, 0.0)),
-                        transform: Transform {
-                            rotation: Quat::from_rotation_z(PI / 2.0),
-                            translation: Vec3 {
-                                x: j as f32 * (rectangle_width + window.width() / 40.),
-                                y: i as f32 * (rectangle_height + window.height() / 30.)
-                                    + window.height() / 4.0,
-                                z: 0.0,
-                            },
-                            scale: Vec3 {
-                                x: 1.0,
-                                y: 1.0,
-                                z: 1.0,
-                            },
-                        },
-                        ..default()
-                    },
-                    state,
-                });
-            }
+        let grid = LevelGrid::new(&field);
+        let rectangle_width = grid.brick_width;
+        let rectangle_height = grid.brick_height;
+        let rectangle =
+            Mesh2dHandle(meshes.add(upright_uvs(Rectangle::new(rectangle_height, rectangle_width).into())));
+        let layout = current_layout(&game_state, &level);
+        let lowest_row = layout.bricks.iter().map(|brick| brick.row).min().unwrap_or(0);
+        for brick in &layout.bricks {
+            let state = BrickState {
+                width: rectangle_width,
+                height: rectangle_height,
+                hit_bar: brick.hit_points,
+                max_hit_bar: brick.hit_points,
+                row: brick.row - lowest_row,
+            };
+            let position = grid.position(brick.column, brick.row);
+            let material = brick_materials.get(&state, &theme, &mut materials);
+            commands.spawn(BrickBundle::new(state, position)).with_children(|parent| {
+                parent.spawn(BrickVisualBundle::new(rectangle.clone(), material));
+            });
         }
     }
 }
@@ -0,0 +1,51 @@
+use bevy::prelude::*;
+use bevy::window::{PresentMode, PrimaryWindow, WindowMode};
+use serde::{Deserialize, Serialize};
+
+// window options and the size of the text, applied whenever they change
+pub struct DisplayPlugin;
+
+// the choices of the text size setting
+pub const TEXT_SCALE_RANGE: (f32, f32) = (0.5, 2.0);
+
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    // borderless on the whole screen, the camera scales the playing field to fit
+    pub fullscreen: bool,
+    pub vsync: bool,
+    // accessibility: scales the text and buttons of every screen
+    pub text_scale: f32,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        DisplaySettings {
+            fullscreen: false,
+            vsync: true,
+            text_scale: 1.0,
+        }
+    }
+}
+
+impl Plugin for DisplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DisplaySettings>()
+            .add_systems(Update, apply_display_settings);
+    }
+}
+
+fn apply_display_settings(
+    settings: Res<DisplaySettings>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+    mut ui_scale: ResMut<UiScale>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    if let Ok(mut window) = window_query.get_single_mut() {
+        window.mode = if settings.fullscreen { WindowMode::BorderlessFullscreen } else { WindowMode::Windowed };
+        window.present_mode = if settings.vsync { PresentMode::AutoVsync } else { PresentMode::AutoNoVsync };
+    }
+    ui_scale.0 = settings.text_scale;
+}
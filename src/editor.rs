@@ -0,0 +1,288 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_xpbd_2d::prelude::*;
+
+use crate::create_objects::{BallState, PlayerRectangleState, PlayingField};
+use crate::game_logic::{GameMode, GameState, HideContainersEvent, RecreateBricksEvent, StartDemoEvent};
+use crate::level::{BrickPlacement, Level, LevelGrid, LEVEL_PATH, MAX_COLUMN, MAX_HIT_POINTS, MAX_ROW, MIN_COLUMN, MIN_ROW};
+use crate::theme::Theme;
+use crate::ui::NewGameEvent;
+
+// edits the level on a snap grid, reachable from the start screen
+pub struct EditorPlugin;
+
+#[derive(Event)]
+pub struct OpenEditorEvent;
+
+#[derive(Resource)]
+struct EditorState {
+    // hit points of newly placed bricks
+    hit_points: i32,
+    // a test game was started from the editor, Escape returns to it
+    testing: bool,
+    // result of the last save or load
+    status: String,
+}
+
+impl Default for EditorState {
+    fn default() -> Self {
+        EditorState {
+            hit_points: 1,
+            testing: false,
+            status: String::new(),
+        }
+    }
+}
+
+#[derive(Component)]
+struct EditorHelp;
+
+const HIT_POINT_KEYS: [KeyCode; 5] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+];
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EditorState>()
+            .add_event::<OpenEditorEvent>()
+            .add_systems(Startup, setup)
+            // the editor opens after this frame's input was handled, so the click or key
+            // that opened it doesn't also edit the level or close it again
+            .add_systems(Update, (leave_test_game, editor_commands, edit_level, open_editor, draw_grid, editor_help).chain());
+    }
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("embedded://fonts/AgaveNerdFont-Regular.ttf"),
+                font_size: 20.0,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            left: Val::Px(5.0),
+            ..default()
+        }),
+        Label,
+        EditorHelp,
+    ));
+}
+
+fn leave_test_game(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    game_state: Res<GameState>,
+    editor_state: Res<EditorState>,
+    mut open_editor_event: EventWriter<OpenEditorEvent>,
+) {
+    if editor_state.testing && !game_state.demo && !game_state.editing && keyboard_input.just_pressed(KeyCode::Escape) {
+        open_editor_event.send(OpenEditorEvent);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn open_editor(
+    mut open_editor_event: EventReader<OpenEditorEvent>,
+    mut game_state: ResMut<GameState>,
+    mut editor_state: ResMut<EditorState>,
+    mut recreate_bricks_event: EventWriter<RecreateBricksEvent>,
+    mut hide_containers_event: EventWriter<HideContainersEvent>,
+    mut ball_query: Query<(&mut LinearVelocity, &mut Transform, &mut BallState), Without<PlayerRectangleState>>,
+    mut player_rectangle_query: Query<(&mut Transform, &PlayerRectangleState)>,
+    mut physics_time: ResMut<Time<Physics>>,
+) {
+    if open_editor_event.read().count() == 0 {
+        return;
+    }
+    game_state.in_game = false;
+    game_state.demo = false;
+    game_state.paused = false;
+    game_state.editing = true;
+    game_state.seed = None;
+    // the editor shows the level as a classic game plays it, with one paddle
+    game_state.mode = GameMode::Classic;
+    editor_state.testing = false;
+    editor_state.status.clear();
+    // nothing moves while editing, the ball waits on the paddle
+    physics_time.pause();
+    for (mut linear_velocity, mut transform, mut ball_state) in &mut ball_query {
+        ball_state.active = false;
+        linear_velocity.0 = Vec2::ZERO;
+        transform.translation.x = ball_state.initial_position.x;
+        transform.translation.y = ball_state.initial_position.y;
+    }
+    for (mut transform, rectangle_state) in &mut player_rectangle_query {
+        transform.translation.x = (rectangle_state.min_x + rectangle_state.max_x) / 2.0;
+    }
+    recreate_bricks_event.send(RecreateBricksEvent);
+    hide_containers_event.send(HideContainersEvent);
+}
+
+// the grid cell under the mouse
+fn hovered_cell(
+    window_query: &Query<&Window, With<PrimaryWindow>>,
+    camera_query: &Query<(&Camera, &GlobalTransform)>,
+    field: &PlayingField,
+) -> Option<(i32, i32)> {
+    let window = window_query.get_single().ok()?;
+    let (camera, camera_transform) = camera_query.get_single().ok()?;
+    let cursor = window.cursor_position()?;
+    let position = camera.viewport_to_world_2d(camera_transform, cursor)?;
+    LevelGrid::new(field).cell_at(position)
+}
+
+// left click places a brick or cycles its hit points, right click erases,
+// the number keys pick the hit points for new bricks and the hovered one
+#[allow(clippy::too_many_arguments)]
+fn edit_level(
+    game_state: Res<GameState>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    mut editor_state: ResMut<EditorState>,
+    mut level: ResMut<Level>,
+    mut recreate_bricks_event: EventWriter<RecreateBricksEvent>,
+    field: Res<PlayingField>,
+) {
+    if !game_state.editing {
+        return;
+    }
+    let hovered = hovered_cell(&window_query, &camera_query, &field);
+    let mut changed = false;
+    if let Some(index) = HIT_POINT_KEYS.iter().position(|key| keyboard_input.just_pressed(*key)) {
+        editor_state.hit_points = index as i32 + 1;
+        if let Some(brick) = hovered.and_then(|(column, row)| level.brick_at(column, row)) {
+            level.bricks[brick].hit_points = editor_state.hit_points;
+            changed = true;
+        }
+    }
+    if let Some((column, row)) = hovered {
+        let brick = level.brick_at(column, row);
+        if mouse_input.just_pressed(MouseButton::Left) {
+            match brick {
+                Some(brick) => {
+                    let hit_points = &mut level.bricks[brick].hit_points;
+                    *hit_points = *hit_points % MAX_HIT_POINTS + 1;
+                }
+                None => level.bricks.push(BrickPlacement {
+                    column,
+                    row,
+                    hit_points: editor_state.hit_points,
+                }),
+            }
+            changed = true;
+        } else if mouse_input.just_pressed(MouseButton::Right) {
+            if let Some(brick) = brick {
+                level.bricks.remove(brick);
+                changed = true;
+            }
+        }
+    }
+    if changed {
+        recreate_bricks_event.send(RecreateBricksEvent);
+    }
+}
+
+// S saves, L loads, C clears, T test-plays the level and Escape goes back to the start screen
+fn editor_commands(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut game_state: ResMut<GameState>,
+    mut editor_state: ResMut<EditorState>,
+    mut level: ResMut<Level>,
+    mut recreate_bricks_event: EventWriter<RecreateBricksEvent>,
+    mut new_game_event: EventWriter<NewGameEvent>,
+    mut start_demo_event: EventWriter<StartDemoEvent>,
+) {
+    if !game_state.editing {
+        return;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyS) {
+        editor_state.status = match level.save(LEVEL_PATH) {
+            Ok(()) => format!("saved {}", LEVEL_PATH),
+            Err(error) => format!("could not save {}: {}", LEVEL_PATH, error),
+        };
+    } else if keyboard_input.just_pressed(KeyCode::KeyL) {
+        editor_state.status = match Level::load(LEVEL_PATH) {
+            Ok(loaded) => {
+                *level = loaded;
+                recreate_bricks_event.send(RecreateBricksEvent);
+                format!("loaded {}", LEVEL_PATH)
+            }
+            Err(error) => format!("could not load {}: {}", LEVEL_PATH, error),
+        };
+    } else if keyboard_input.just_pressed(KeyCode::KeyC) {
+        level.bricks.clear();
+        recreate_bricks_event.send(RecreateBricksEvent);
+    } else if keyboard_input.just_pressed(KeyCode::KeyT) {
+        // an empty level would be won right away
+        if level.bricks.is_empty() {
+            editor_state.status = "place at least one brick first".to_string();
+        } else {
+            editor_state.testing = true;
+            new_game_event.send(NewGameEvent::default());
+        }
+    } else if keyboard_input.just_pressed(KeyCode::Escape) {
+        game_state.editing = false;
+        editor_state.testing = false;
+        start_demo_event.send(StartDemoEvent);
+    }
+}
+
+fn draw_grid(
+    mut gizmos: Gizmos,
+    game_state: Res<GameState>,
+    editor_state: Res<EditorState>,
+    theme: Res<Theme>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    field: Res<PlayingField>,
+) {
+    if !game_state.editing {
+        return;
+    }
+    let grid = LevelGrid::new(&field);
+    let size = Vec2::new(grid.brick_width, grid.brick_height);
+    for column in MIN_COLUMN..=MAX_COLUMN {
+        for row in MIN_ROW..=MAX_ROW {
+            gizmos.rect_2d(grid.position(column, row), 0.0, size, theme.text.with_a(0.2));
+        }
+    }
+    if let Some((column, row)) = hovered_cell(&window_query, &camera_query, &field) {
+        gizmos.rect_2d(grid.position(column, row), 0.0, size * 1.1, theme.brick(editor_state.hit_points));
+    }
+}
+
+fn editor_help(
+    game_state: Res<GameState>,
+    editor_state: Res<EditorState>,
+    level: Res<Level>,
+    mut text_query: Query<&mut Text, With<EditorHelp>>,
+) {
+    if !(game_state.is_changed() || editor_state.is_changed() || level.is_changed()) {
+        return;
+    }
+    let mut text = text_query.single_mut();
+    text.sections[0].value = if game_state.editing {
+        format!(
+            "Left click: place / cycle brick   Right click: erase   1-{}: hit points ({})\n\
+             S: save   L: load   C: clear   T: test play   Esc: back   Bricks: {}   {}",
+            MAX_HIT_POINTS,
+            editor_state.hit_points,
+            level.bricks.len(),
+            editor_state.status,
+        )
+    } else if editor_state.testing && !game_state.demo {
+        "Esc: back to the editor".to_string()
+    } else {
+        String::new()
+    };
+}
@@ -0,0 +1,119 @@
+use bevy::{prelude::*, sprite::Mesh2dHandle};
+use std::time::Duration;
+
+use crate::brick_damage::BrickMaterials;
+use crate::create_objects::{upright_uvs, BrickBundle, BrickState, BrickVisualBundle, PlayerRectangleState, PlayingField};
+use crate::game_logic::{GameMode, GameOverEvent, GameState};
+use crate::level::{LevelGrid, MAX_ROW};
+use crate::level_generator::{generate_row, ENDLESS_START_ROWS};
+use crate::theme::Theme;
+use crate::ui::NewGameEvent;
+
+// new rows of bricks come in at the top and push the others down
+pub struct EndlessPlugin;
+
+// seconds between rows at the start, every row comes a little sooner than the last
+const FIRST_ROW_SECS: f32 = 12.0;
+const ROW_SECS_STEP: f32 = 0.3;
+const MIN_ROW_SECS: f32 = 5.0;
+
+#[derive(Resource)]
+pub struct EndlessState {
+    // rows generated so far, including the starting ones
+    pub rows: u32,
+    pub row_timer: Timer,
+}
+
+impl Default for EndlessState {
+    fn default() -> Self {
+        EndlessState {
+            rows: ENDLESS_START_ROWS,
+            row_timer: Timer::new(Duration::from_secs_f32(FIRST_ROW_SECS), TimerMode::Once),
+        }
+    }
+}
+
+impl Plugin for EndlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EndlessState>()
+            .add_systems(Update, (reset_endless_state, add_rows, bricks_reached_paddle).chain());
+    }
+}
+
+fn reset_endless_state(mut new_game_event: EventReader<NewGameEvent>, mut endless_state: ResMut<EndlessState>) {
+    if new_game_event.read().count() > 0 {
+        *endless_state = EndlessState::default();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn add_rows(
+    mut commands: Commands,
+    mut game_state: ResMut<GameState>,
+    mut endless_state: ResMut<EndlessState>,
+    mut brick_query: Query<&mut Transform, With<BrickState>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut brick_materials: ResMut<BrickMaterials>,
+    theme: Res<Theme>,
+    field: Res<PlayingField>,
+    time: Res<Time>,
+) {
+    if game_state.mode != GameMode::Endless || !game_state.in_game || game_state.paused {
+        return;
+    }
+    if !endless_state.row_timer.tick(time.delta()).finished() {
+        return;
+    }
+    let grid = LevelGrid::new(&field);
+    for mut transform in &mut brick_query {
+        transform.translation.y -= grid.row_spacing();
+    }
+    let seed = game_state.seed.unwrap_or_default();
+    let row = generate_row(seed, endless_state.rows, MAX_ROW);
+    let mesh = Mesh2dHandle(meshes.add(upright_uvs(Rectangle::new(grid.brick_height, grid.brick_width).into())));
+    for brick in &row {
+        let state = BrickState {
+            width: grid.brick_width,
+            height: grid.brick_height,
+            hit_bar: brick.hit_points,
+            max_hit_bar: brick.hit_points,
+            // as high as the top starting row
+            row: ENDLESS_START_ROWS as i32 - 1,
+        };
+        let material = brick_materials.get(&state, &theme, &mut materials);
+        commands.spawn(BrickBundle::new(state, grid.position(brick.column, brick.row))).with_children(|parent| {
+            parent.spawn(BrickVisualBundle::new(mesh.clone(), material));
+        });
+    }
+    game_state.add_bricks(row.len() as i32);
+    endless_state.rows += 1;
+    let interval = FIRST_ROW_SECS - ROW_SECS_STEP * (endless_state.rows - ENDLESS_START_ROWS) as f32;
+    endless_state.row_timer = Timer::new(Duration::from_secs_f32(interval.max(MIN_ROW_SECS)), TimerMode::Once);
+}
+
+fn bricks_reached_paddle(
+    mut game_state: ResMut<GameState>,
+    mut game_over_event: EventWriter<GameOverEvent>,
+    brick_query: Query<(&Transform, &BrickState)>,
+    player_rectangle_query: Query<(&Transform, &PlayerRectangleState)>,
+) {
+    if game_state.mode != GameMode::Endless || !game_state.in_game {
+        return;
+    }
+    let Some(paddle_top) = player_rectangle_query
+        .iter()
+        .map(|(transform, rectangle_state)| transform.translation.y + rectangle_state.height / 2.0)
+        .reduce(f32::max)
+    else {
+        return;
+    };
+    let reached = brick_query
+        .iter()
+        .any(|(transform, brick_state)| transform.translation.y - brick_state.height / 2.0 <= paddle_top);
+    if reached {
+        // leaving in_game makes sure the event is only sent once per game
+        game_state.in_game = false;
+        game_over_event.send(GameOverEvent);
+    }
+}
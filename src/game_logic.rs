@@ -1,13 +1,115 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::create_objects::{BallState, BrickState, PlayerRectangleState, Floor};
+use crate::create_objects::{BallState, BrickState, BrickVisual, PlayerRectangleState, Floor, Walls};
+use std::f32::consts::FRAC_PI_2;
 use bevy_xpbd_2d::prelude::*;
 
+use crate::autopilot::Autopilot;
+use crate::brick_damage::{BrickFlash, BrickMaterials};
+use crate::coop::CoopSettings;
+use crate::level::Level;
+use crate::level_generator::{current_layout, GENERATED_LEVELS};
+use crate::theme::Theme;
+use crate::time_attack::{time_bonus, TIME_ATTACK_START_SECS};
 use crate::ui::NewGameEvent;
 
 
 pub struct GameLogicPlugin;
 
+// smallest angle (radians) the ball may travel at relative to either axis
+const MIN_BALL_ANGLE: f32 = 0.2;
+// rotation applied when the ball has not touched the paddle or a brick in time
+const STUCK_NUDGE_ANGLE: f32 = 0.3;
+// lives at the start of a game, per player when co-op players have their own
+pub const STARTING_LIVES: i32 = 3;
+// horizontal paddle movement per frame
+const PADDLE_STEP: f32 = 15.0;
+// the choices of the difficulty settings
+pub const BALL_SPEED_RANGE: (f32, f32) = (400.0, 1600.0);
+pub const LIVES_RANGE: (i32, i32) = (1, 9);
+
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DifficultySettings {
+    // units per second, applied when the next game starts
+    pub ball_speed: f32,
+    // lives at the start of a game, per player when co-op players have their own
+    pub lives: i32,
+}
+
+impl Default for DifficultySettings {
+    fn default() -> Self {
+        DifficultySettings {
+            ball_speed: 800.0,
+            lives: STARTING_LIVES,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerKeys {
+    pub left: KeyCode,
+    pub right: KeyCode,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Left(usize),
+    Right(usize),
+    Launch,
+}
+
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControlSettings {
+    // the first player also plays alone
+    pub players: [PlayerKeys; 2],
+    pub launch: KeyCode,
+}
+
+impl Default for ControlSettings {
+    fn default() -> Self {
+        ControlSettings {
+            players: [
+                PlayerKeys { left: KeyCode::ArrowLeft, right: KeyCode::ArrowRight },
+                PlayerKeys { left: KeyCode::KeyA, right: KeyCode::KeyD },
+            ],
+            launch: KeyCode::Space,
+        }
+    }
+}
+
+impl ControlSettings {
+    pub const CONTROLS: [Control; 5] = [
+        Control::Left(0),
+        Control::Right(0),
+        Control::Left(1),
+        Control::Right(1),
+        Control::Launch,
+    ];
+
+    // keys that always do the same thing, the autopilot, mute and leaving the editor or a
+    // test game, a control on one of them would do both
+    pub const RESERVED_KEYS: [KeyCode; 3] = [KeyCode::KeyB, KeyCode::KeyM, KeyCode::Escape];
+
+    pub fn key(&self, control: Control) -> KeyCode {
+        match control {
+            Control::Left(player) => self.players[player].left,
+            Control::Right(player) => self.players[player].right,
+            Control::Launch => self.launch,
+        }
+    }
+
+    pub fn key_mut(&mut self, control: Control) -> &mut KeyCode {
+        match control {
+            Control::Left(player) => &mut self.players[player].left,
+            Control::Right(player) => &mut self.players[player].right,
+            Control::Launch => &mut self.launch,
+        }
+    }
+}
+
 #[derive(Event)]
 pub struct GameOverEvent;
 
@@ -20,240 +122,795 @@ pub struct RecreateBricksEvent;
 #[derive(Event)]
 pub struct HideContainersEvent;
 
+#[derive(Event)]
+pub struct StartDemoEvent;
+
+// sent once per contact, derived from the physics CollisionStarted events
+#[derive(Event)]
+pub struct BrickHitEvent {
+    pub entity: Entity,
+    pub ball: Entity,
+    // where the ball touched the brick, for effects subscribing to the event
+    pub position: Vec2,
+}
+
+#[derive(Event)]
+pub struct BrickDestroyedEvent {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub row: i32,
+    pub color: Color,
+}
+
+#[derive(Event)]
+pub struct PaddleHitEvent {
+    pub ball: Entity,
+    pub paddle: Entity,
+    pub position: Vec2,
+}
+
+#[derive(Event)]
+pub struct WallHitEvent {
+    pub ball: Entity,
+    // where the ball touched the wall
+    pub position: Vec2,
+}
+
+#[derive(Event)]
+pub struct BallLostEvent {
+    pub ball: Entity,
+    pub position: Vec2,
+    // the player defending the line the ball crossed
+    pub player: usize,
+}
+
+// what a player asks of their paddle this frame, from the keyboard or over the network
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug, Serialize, Deserialize)]
+pub struct PaddleInput {
+    pub left: bool,
+    pub right: bool,
+    pub launch: bool,
+}
+
+// this frame's input of every player, indexed like PlayerRectangleState::player
+#[derive(Resource, Default)]
+pub struct PlayerInputs(pub [PaddleInput; 2]);
+
+// systems that fill in PlayerInputs, the ones acting on it run after them
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReadInputs;
+
+#[derive(Resource)]
+pub struct PaddleDeflection {
+    // bounce angle from vertical (radians) when the ball hits the paddle edge
+    pub max_bounce_angle: f32,
+    // share of the paddle velocity added to the ball ("english"), 0 disables it
+    pub english: f32,
+    // smallest upward component of the normalized direction after a bounce
+    pub min_vertical: f32,
+}
+
+impl Default for PaddleDeflection {
+    fn default() -> Self {
+        PaddleDeflection {
+            max_bounce_angle: std::f32::consts::PI / 3.0,
+            english: 0.3,
+            min_vertical: 0.4,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug, Serialize, Deserialize)]
+pub enum GameMode {
+    #[default]
+    Classic,
+    // rows keep coming down from the top until they reach the paddle
+    Endless,
+    // the level has to be cleared before a countdown runs out
+    TimeAttack,
+    // two paddles side by side
+    Coop,
+    // one paddle at the bottom and one at the top, each defending a goal line
+    Versus,
+}
+
+// rules fixed for the whole game when it starts, changing the settings only affects the next one
+#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct GameRules {
+    pub shared_lives: bool,
+    pub ball_speed: f32,
+    pub lives: i32,
+}
+
+impl GameRules {
+    // the settings decide, unless the game brings its own rules like an online guest's
+    pub fn for_new_game(
+        new_game: Option<&NewGameEvent>,
+        coop_settings: &CoopSettings,
+        difficulty: &DifficultySettings,
+    ) -> Self {
+        new_game.and_then(|event| event.rules).unwrap_or(GameRules {
+            shared_lives: coop_settings.shared_lives,
+            ball_speed: difficulty.ball_speed,
+            lives: difficulty.lives,
+        })
+    }
+
+    // rules sent by the other side, kept within what the settings allow
+    pub fn clamped(self) -> Self {
+        let (min_speed, max_speed) = BALL_SPEED_RANGE;
+        let (min_lives, max_lives) = LIVES_RANGE;
+        let ball_speed = if self.ball_speed.is_nan() {
+            DifficultySettings::default().ball_speed
+        } else {
+            self.ball_speed.clamp(min_speed, max_speed)
+        };
+        GameRules {
+            shared_lives: self.shared_lives,
+            ball_speed,
+            lives: self.lives.clamp(min_lives, max_lives),
+        }
+    }
+}
+
 #[derive(Resource)]
 pub struct GameState {
     pub lives: i32,
     pub in_game: bool,
+    // attract mode: the bot plays behind the start screen
+    pub demo: bool,
+    // a networked game, the simulation only moves on with the inputs of both players
+    pub online: bool,
+    pub paused: bool,
+    // the level editor is open, nothing moves
+    pub editing: bool,
+    pub level: u32,
+    pub mode: GameMode,
+    pub score: u32,
+    // co-op and versus share of the score, by the player who last touched the ball
+    pub player_scores: [u32; 2],
+    // lives of each player in versus and in co-op when every player has their own,
+    // lives is their sum then
+    pub player_lives: [i32; 2],
+    // seconds played, not counting pauses
+    pub elapsed: f32,
+    // countdown of the time attack mode
+    pub time_left: f32,
+    // seed of the generated level being played, None for the loaded level
+    pub seed: Option<u32>,
+    // bricks hit since the ball last touched the paddle
+    pub combo: u32,
+    pub rules: GameRules,
     bricks: i32,
 }
 
+impl GameState {
+    // for bricks spawned during a game, e.g. the rows of the endless mode
+    pub fn add_bricks(&mut self, count: i32) {
+        self.bricks += count;
+    }
+
+    // for a saved game that is continued with the bricks it had left
+    pub fn set_bricks(&mut self, count: i32) {
+        self.bricks = count;
+    }
+}
+
 impl Plugin for GameLogicPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
+        app.add_systems(Startup, start_demo)
+        .add_systems(
             Update,
             (
-                activate_ball,
-                on_collision_with_brick,
-                on_collision_with_floor,
+                track_play_time,
+                read_keyboard_inputs.in_set(ReadInputs),
+                activate_ball.after(ReadInputs),
+                resume_game.after(activate_ball),
                 move_inactive_ball,
-                move_player_rectangle,
+                move_player_rectangle.after(ReadInputs),
                 game_won,
                 game_over,
                 reset_game_state,
                 (
-                    on_collision_with_player_rectangle,
-                    remove_brick
+                    track_player_rectangle_velocity,
+                    detect_collisions,
+                    (
+                        on_collision_with_player_rectangle,
+                        on_collision_with_brick,
+                        on_collision_with_floor,
+                    ),
+                    // after the handlers, so a bounce off the paddle can't undo the nudge
+                    unstick_ball,
                 ).chain()
             ),
         )
-        .insert_resource(GameState { lives: 3, in_game: false, bricks: 25 })
+        .insert_resource(GameState { lives: STARTING_LIVES, in_game: false, demo: true, online: false, paused: false, editing: false, level: 1, mode: GameMode::Classic, score: 0, player_scores: [0; 2], player_lives: [STARTING_LIVES; 2], elapsed: 0.0, time_left: 0.0, seed: None, combo: 0, rules: GameRules::default(), bricks: 25 })
+        .init_resource::<PaddleDeflection>()
+        .init_resource::<DifficultySettings>()
+        .init_resource::<ControlSettings>()
+        .init_resource::<PlayerInputs>()
         .add_event::<GameOverEvent>()
         .add_event::<GameWonEvent>()
         .add_event::<RecreateBricksEvent>()
-        .add_event::<HideContainersEvent>();
+        .add_event::<HideContainersEvent>()
+        .add_event::<StartDemoEvent>()
+        .add_event::<BrickHitEvent>()
+        .add_event::<BrickDestroyedEvent>()
+        .add_event::<PaddleHitEvent>()
+        .add_event::<WallHitEvent>()
+        .add_event::<BallLostEvent>();
     }
 }
 
-fn activate_ball(
-    mut ball_query: Query<(&mut BallState, &mut LinearVelocity)>, 
+fn start_demo(mut start_demo_event: EventWriter<StartDemoEvent>) {
+    start_demo_event.send(StartDemoEvent);
+}
+
+// a paused game, i.e. a continued one, goes on once a player presses the launch key,
+// after activate_ball so the same press doesn't also serve the ball
+fn resume_game(
+    player_inputs: Res<PlayerInputs>,
+    mut game_state: ResMut<GameState>,
+    mut physics_time: ResMut<Time<Physics>>,
+) {
+    if game_state.paused && player_inputs.0.iter().any(|input| input.launch) {
+        game_state.paused = false;
+        physics_time.unpause();
+    }
+}
+
+fn track_play_time(mut game_state: ResMut<GameState>, time: Res<Time>) {
+    if game_state.in_game && !game_state.paused && !game_state.demo {
+        game_state.elapsed += time.delta_seconds();
+    }
+}
+
+// each player steers with their own keys, any of them launches the ball with the shared key
+fn read_keyboard_inputs(
     keyboard_input: Res<ButtonInput<KeyCode>>,
+    controls: Res<ControlSettings>,
+    mut player_inputs: ResMut<PlayerInputs>,
+    game_state: Res<GameState>,
+) {
+    // the network module hands out the inputs of a networked game
+    if game_state.online {
+        return;
+    }
+    for (input, keys) in player_inputs.0.iter_mut().zip(controls.players) {
+        *input = PaddleInput {
+            left: keyboard_input.pressed(keys.left),
+            right: keyboard_input.pressed(keys.right),
+            launch: keyboard_input.just_pressed(controls.launch),
+        };
+    }
+}
+
+fn activate_ball(
+    mut ball_query: Query<(&mut BallState, &mut LinearVelocity, &Transform)>, 
+    player_rectangle_query: Query<(&Transform, &PlayerRectangleState)>,
+    player_inputs: Res<PlayerInputs>,
     game_state: Res<GameState>,
 ) {
-    if game_state.in_game {
-        let (mut ball_state, mut linear_velocity) = ball_query.single_mut();
-        if keyboard_input.just_pressed(KeyCode::Space) && !ball_state.active {
+    if game_state.in_game && !game_state.paused {
+        let (mut ball_state, mut linear_velocity, transform) = ball_query.single_mut();
+        let launch = player_inputs.0.iter().any(|input| input.launch);
+        if launch && !ball_state.active {
             ball_state.active = true;
             linear_velocity.x = 0.0;
-            linear_velocity.y = 1.0 * ball_state.speed;
+            // away from the serving paddle, down when it is at the top
+            let direction = if transform.translation.y > 0.0 { -1.0 } else { 1.0 };
+            linear_velocity.y = direction * ball_state.speed;
+            // the way the paddle moves, or else the other side than last time
+            let paddle_velocity = serving_paddle(&player_rectangle_query, ball_state.server).map_or(0.0, |(_, state)| state.velocity);
+            ball_state.drift = if paddle_velocity != 0.0 { paddle_velocity.signum() } else { -ball_state.drift };
+        }
+    }
+}
+
+// direction of the ball leaving the paddle, offset is the hit position relative to
+// the paddle centre divided by the paddle width (-0.5 at the left edge, 0.5 at the right)
+pub fn deflect(offset: f32, paddle_velocity: f32, speed: f32, deflection: &PaddleDeflection) -> Vec2 {
+    let angle = (offset * 2.0).clamp(-1.0, 1.0) * deflection.max_bounce_angle;
+    let mut direction = Vec2::new(angle.sin(), angle.cos());
+    if speed > 0.0 {
+        direction.x += paddle_velocity / speed * deflection.english;
+    }
+    direction = direction.normalize_or_zero();
+    if direction.y < deflection.min_vertical {
+        let x_sign = if direction.x < 0.0 { -1.0 } else { 1.0 };
+        direction.y = deflection.min_vertical.min(1.0);
+        direction.x = x_sign * (1.0 - direction.y * direction.y).sqrt();
+    }
+    direction * speed
+}
+
+fn track_player_rectangle_velocity(
+    mut rectangle_query: Query<(&Transform, &mut PlayerRectangleState)>,
+    time: Res<Time>,
+) {
+    for (transform, mut state) in &mut rectangle_query {
+        if time.delta_seconds() > 0.0 {
+            state.velocity = (transform.translation.x - state.previous_x) / time.delta_seconds();
         }
+        state.previous_x = transform.translation.x;
     }
 }
 
-// read more about event readers
+// translate physics contacts involving a ball into game events
+#[allow(clippy::too_many_arguments)]
+fn detect_collisions(
+    mut collision_started: EventReader<CollisionStarted>,
+    collisions: Res<Collisions>,
+    ball_query: Query<(&Position, &Rotation), With<BallState>>,
+    brick_query: Query<(), With<BrickState>>,
+    player_rectangle_query: Query<(), With<PlayerRectangleState>>,
+    floor_query: Query<&Floor>,
+    walls_query: Query<(), With<Walls>>,
+    mut brick_hit_event: EventWriter<BrickHitEvent>,
+    mut paddle_hit_event: EventWriter<PaddleHitEvent>,
+    mut wall_hit_event: EventWriter<WallHitEvent>,
+    mut ball_lost_event: EventWriter<BallLostEvent>,
+    game_state: Res<GameState>,
+) {
+    for CollisionStarted(first, second) in collision_started.read() {
+        let (ball, other, (ball_position, ball_rotation)) = if let Ok(body) = ball_query.get(*first) {
+            (*first, *second, body)
+        } else if let Ok(body) = ball_query.get(*second) {
+            (*second, *first, body)
+        } else {
+            continue;
+        };
+        let position = ball_position.0;
+        let contact = contact_point(&collisions, ball, other, ball_position, ball_rotation).unwrap_or(position);
+        if brick_query.contains(other) {
+            brick_hit_event.send(BrickHitEvent { entity: other, ball, position: contact });
+        } else if player_rectangle_query.contains(other) {
+            paddle_hit_event.send(PaddleHitEvent { ball, paddle: other, position });
+        } else if let Ok(floor) = floor_query.get(other) {
+            // the top line is only a goal in versus games, a wall otherwise
+            if floor.player == 0 || game_state.mode == GameMode::Versus {
+                ball_lost_event.send(BallLostEvent { ball, position, player: floor.player });
+            } else {
+                wall_hit_event.send(WallHitEvent { ball, position: contact });
+            }
+        } else if walls_query.contains(other) {
+            wall_hit_event.send(WallHitEvent { ball, position: contact });
+        }
+    }
+}
+
+// the deepest point where the ball touches the other body, in world space
+fn contact_point(collisions: &Collisions, ball: Entity, other: Entity, position: &Position, rotation: &Rotation) -> Option<Vec2> {
+    let contacts = collisions.get(ball, other)?;
+    let contact = contacts
+        .manifolds
+        .iter()
+        .flat_map(|manifold| &manifold.contacts)
+        .max_by(|a, b| a.penetration.total_cmp(&b.penetration))?;
+    let local = if contacts.entity1 == ball { contact.point1 } else { contact.point2 };
+    Some(position.0 + rotation.rotate(local))
+}
+
 fn on_collision_with_player_rectangle(
-    mut linear_velocity_query: Query<&mut LinearVelocity, With<BallState>>,
-    ball_query: Query<(&GlobalTransform, &BallState)>,
+    mut paddle_hit_event: EventReader<PaddleHitEvent>,
+    mut ball_query: Query<(&mut LinearVelocity, &mut BallState)>,
     rectangle_query: Query<(&GlobalTransform, &PlayerRectangleState)>,
-    colliding_entities_query: Query<&CollidingEntities, With<PlayerRectangleState>>,
+    deflection: Res<PaddleDeflection>,
+    mut game_state: ResMut<GameState>,
 ) {
-    let mut linear_velocity = linear_velocity_query.single_mut();
-
-    if let Ok(colliding_entities) = colliding_entities_query.get_single() {
-        if !(colliding_entities.0.is_empty()) {
-            let (ball_global_transform, ball_state) = ball_query.single();
-            let (rectangle_global_transform, rectangle_state) = rectangle_query.single();
-            let ball_x = ball_global_transform.translation().x;
-            let rectangle_x = rectangle_global_transform.translation().x;
-            let rectangle_width = rectangle_state.width;
-            // value between -1 and 1
-            let x_direction = (ball_x - rectangle_x) / rectangle_width;
-            // normalize y
-            *linear_velocity = LinearVelocity(linear_velocity.normalize());
-            linear_velocity.x = x_direction;
-            // normalize again to get back to original speed
-            *linear_velocity = LinearVelocity(linear_velocity.normalize());
-            linear_velocity.x = linear_velocity.x * ball_state.speed;
-            linear_velocity.y = linear_velocity.y * ball_state.speed;
+    for event in paddle_hit_event.read() {
+        game_state.combo = 0;
+        let Ok((mut linear_velocity, mut ball_state)) = ball_query.get_mut(event.ball) else {
+            continue;
+        };
+        let Ok((rectangle_global_transform, rectangle_state)) = rectangle_query.get(event.paddle) else {
+            continue;
+        };
+        ball_state.last_player = rectangle_state.player;
+        let rectangle_x = rectangle_global_transform.translation().x;
+        // value between -0.5 and 0.5
+        let offset = (event.position.x - rectangle_x) / rectangle_state.width;
+        linear_velocity.0 = deflect(offset, rectangle_state.velocity, ball_state.speed, &deflection);
+        // a paddle at the top sends the ball down
+        if rectangle_global_transform.translation().y > 0.0 {
+            linear_velocity.y = -linear_velocity.y;
         }
     }
 }
 
 fn on_collision_with_floor(
-    mut floor_query: Query<(&CollidingEntities, &mut Floor)>,
+    mut ball_lost_event: EventReader<BallLostEvent>,
     mut game_state: ResMut<GameState>,
-    mut ball_query: Query<(&mut LinearVelocity, &mut Transform, &mut BallState)>,
-    player_rectangle_query: Query<&GlobalTransform, With<PlayerRectangleState>>,
-    time: Res<Time>,
+    mut ball_query: Query<(&mut LinearVelocity, &mut Transform, &mut BallState), Without<PlayerRectangleState>>,
+    player_rectangle_query: Query<(&Transform, &PlayerRectangleState)>,
 ) {
-    if let Ok((colliding_entities, mut floor_state)) = floor_query.get_single_mut() {
-        floor_state.hit_timer.tick(time.delta());
-        // only react to first collision
-        if !(colliding_entities.0.is_empty()) && floor_state.hit_timer.elapsed_secs() > 0.05 {
-            floor_state.hit_timer.reset();
-            game_state.lives -= 1;
-            let (mut linear_velocity, mut transform, mut ball_state) = ball_query.single_mut();
-            let rectangle_global_transform = player_rectangle_query.single().translation();
-            ball_state.active = false;
-            linear_velocity.x = 0.0;
-            linear_velocity.y = 0.0;
-            transform.translation.x = ball_state.initial_position.x + rectangle_global_transform.x;
-            transform.translation.y = ball_state.initial_position.y;
-        }
+    for event in ball_lost_event.read() {
+        let Ok((mut linear_velocity, mut transform, mut ball_state)) = ball_query.get_mut(event.ball) else {
+            continue;
+        };
+        game_state.lives -= 1;
+        game_state.combo = 0;
+        // in versus the player who conceded serves
+        ball_state.server = if game_state.mode == GameMode::Versus { event.player } else { 0 };
+        let rectangle = serving_paddle(&player_rectangle_query, ball_state.server);
+        ball_state.active = false;
+        linear_velocity.x = 0.0;
+        linear_velocity.y = 0.0;
+        let position = serve_position(&ball_state, rectangle.map(|(transform, _)| transform));
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
     }
 }
+
+// the paddle the ball waits on, the first player's when the server's is gone
+pub fn serving_paddle<'a>(
+    rectangle_query: &'a Query<(&Transform, &PlayerRectangleState)>,
+    server: usize,
+) -> Option<(&'a Transform, &'a PlayerRectangleState)> {
+    rectangle_query
+        .iter()
+        .find(|(_, state)| state.player == server)
+        .or_else(|| rectangle_query.iter().min_by_key(|(_, state)| state.player))
+}
+
+// where the ball waits on the serving paddle, below it when the paddle is at the top
+fn serve_position(ball_state: &BallState, rectangle_transform: Option<&Transform>) -> Vec2 {
+    let Some(rectangle_transform) = rectangle_transform else {
+        return ball_state.initial_position.truncate();
+    };
+    let y = if rectangle_transform.translation.y > 0.0 {
+        -ball_state.initial_position.y
+    } else {
+        ball_state.initial_position.y
+    };
+    Vec2::new(ball_state.initial_position.x + rectangle_transform.translation.x, y)
+}
+
 fn move_inactive_ball(
-    mut ball_query: Query<(&mut Transform, &GlobalTransform, &BallState)>,
-    player_rectangle_query: Query<&PlayerRectangleState>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    window: Query<&Window>,
+    mut ball_query: Query<(&mut Transform, &BallState), Without<PlayerRectangleState>>,
+    player_rectangle_query: Query<(&Transform, &PlayerRectangleState)>,
     game_state: Res<GameState>,
 ) {
-    if game_state.in_game {
-        let (mut transform, global_transform, ball_state) = ball_query.single_mut();
-        let window = window.single();
-        let rectangle_width = player_rectangle_query.single().width;
-        if ball_state.active == false {
-            if keyboard_input.pressed(KeyCode::ArrowLeft) && global_transform.translation().x - rectangle_width / 2.0 >= -window.width() / 2.0 {
-                transform.translation += Vec3 {
-                    x: -15.0,
-                    y: 0.0,
-                    z: 0.0,
-                };
-            } else if keyboard_input.pressed(KeyCode::ArrowRight) && global_transform.translation().x + rectangle_width / 2.0 <= window.width() / 2.0{
-                transform.translation += Vec3 {
-                    x: 15.0,
-                    y: 0.0,
-                    z: 0.0,
-                };
+    // the ball rides on the serving paddle until it is launched
+    if game_state.in_game && !game_state.paused {
+        for (mut transform, ball_state) in &mut ball_query {
+            let Some((rectangle_transform, _)) = serving_paddle(&player_rectangle_query, ball_state.server) else {
+                continue;
+            };
+            if !ball_state.active {
+                let position = serve_position(ball_state, Some(rectangle_transform));
+                transform.translation.x = position.x;
+                transform.translation.y = position.y;
             }
         }
     }
 }
 
 fn move_player_rectangle(
-    mut rectangle: Query<(&GlobalTransform, &mut Transform, &PlayerRectangleState)>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    window: Query<&Window>,
+    mut rectangle: Query<(&mut Transform, &PlayerRectangleState)>,
+    player_inputs: Res<PlayerInputs>,
     game_state: Res<GameState>,
+    autopilot: Res<Autopilot>,
 ) {
-    if game_state.in_game {
-        let (global_transform, mut transform, state) = rectangle.single_mut();
-        let window = window.single();
-        if keyboard_input.pressed(KeyCode::ArrowLeft)
-            && global_transform.translation().x - state.width / 2.0 >= -window.width() / 2.0
-        {
-            transform.translation += Vec3 {
-                x: -15.0,
-                y: 0.0,
-                z: 0.0,
-            }; } else if keyboard_input.pressed(KeyCode::ArrowRight)
-            && global_transform.translation().x + state.width / 2.0 <= window.width() / 2.0
-        {
-            transform.translation += Vec3 {
-                x: 15.0,
-                y: 0.0,
-                z: 0.0,
-            };
+    if game_state.in_game && !game_state.paused {
+        // the paddle the bot drives isn't the player's to move
+        let driven = autopilot.driven_player(&game_state, rectangle.iter().map(|(_, state)| state.player));
+        for (mut transform, state) in &mut rectangle {
+            if Some(state.player) == driven {
+                continue;
+            }
+            let input = player_inputs.0[state.player];
+            if input.left {
+                transform.translation.x -= PADDLE_STEP;
+            } else if input.right {
+                transform.translation.x += PADDLE_STEP;
+            }
+            transform.translation.x = transform.translation.x.clamp(state.min_x, state.max_x);
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn on_collision_with_brick(
-    mut brick_query: Query<(&CollidingEntities, &mut BrickState, Entity)>,
+    mut brick_hit_event: EventReader<BrickHitEvent>,
+    mut brick_destroyed_event: EventWriter<BrickDestroyedEvent>,
+    mut brick_query: Query<(&mut BrickState, &GlobalTransform, &Children)>,
+    visual_query: Query<&Handle<ColorMaterial>, With<BrickVisual>>,
+    ball_query: Query<&BallState>,
     mut commands: Commands,
-    time: Res<Time>,
+    mut game_state: ResMut<GameState>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    mut brick_materials: ResMut<BrickMaterials>,
+    theme: Res<Theme>,
 ) {
-
-    for (colliding_entities, mut brick_state, entity) in &mut brick_query {
-        brick_state.hit_timer.tick(time.delta());
-        if !(colliding_entities.0.is_empty()) {
-            // only react to first collision
-            if brick_state.hit_timer.elapsed_secs() > 0.05 {
-                brick_state.hit_bar -= 1;
-                commands.entity(entity).insert(materials.add(Color::rgb(0.0, 1.0 / brick_state.hit_bar as f32, 0.0)));
-                brick_state.hit_timer.reset();
+    for event in brick_hit_event.read() {
+        // a brick can be hit twice before its despawn is applied
+        let Ok((mut brick_state, global_transform, children)) = brick_query.get_mut(event.entity) else {
+            continue;
+        };
+        let visual = children.iter().copied().find(|child| visual_query.contains(*child));
+        if brick_state.hit_bar <= 0 {
+            continue;
+        }
+        brick_state.hit_bar -= 1;
+        game_state.combo += 1;
+        // longer combos are worth more
+        let points = 10 * game_state.combo;
+        game_state.score += points;
+        let player = ball_query.get(event.ball).map_or(0, |ball_state| ball_state.last_player);
+        game_state.player_scores[player] += points;
+        if brick_state.hit_bar == 0 {
+            // the contact has already been resolved by the physics step, so despawning is safe
+            commands.entity(event.entity).despawn_recursive();
+            game_state.bricks -= 1;
+            brick_destroyed_event.send(BrickDestroyedEvent {
+                entity: event.entity,
+                position: global_transform.translation().truncate(),
+                row: brick_state.row,
+                color: visual
+                    .and_then(|visual| materials.get(visual_query.get(visual).ok()?))
+                    .map_or(Color::WHITE, |material| material.color),
+            });
+        } else {
+            let material = brick_materials.get(&brick_state, &theme, &mut materials);
+            if let Some(visual) = visual {
+                commands.entity(visual).insert(material);
             }
+            commands.entity(event.entity).insert(BrickFlash::default());
         }
-
     }
 }
 
-fn remove_brick(
-    mut commands: Commands,
-    mut brick_query: Query<(Entity, &mut BrickState)>,
-    mut game_state: ResMut<GameState>,
+// turn a direction that is too close to horizontal or vertical away from that axis,
+// preserving speed, a vertical ball goes to the side of drift
+pub fn clamp_ball_angle(velocity: Vec2, drift: f32, speed: f32, min_angle: f32) -> Vec2 {
+    let angle = velocity.y.abs().atan2(velocity.x.abs());
+    let clamped = angle.clamp(min_angle, FRAC_PI_2 - min_angle);
+    let x_sign = if velocity.x != 0.0 { velocity.x.signum() } else if drift < 0.0 { -1.0 } else { 1.0 };
+    // prefer sending a flat ball down towards the paddle
+    let y_sign = if velocity.y > 0.0 { 1.0 } else { -1.0 };
+    Vec2::new(x_sign * clamped.cos(), y_sign * clamped.sin()) * speed
+}
+
+fn unstick_ball(
+    mut ball_query: Query<(Entity, &mut LinearVelocity, &mut BallState)>,
+    mut brick_hit_event: EventReader<BrickHitEvent>,
+    mut paddle_hit_event: EventReader<PaddleHitEvent>,
     time: Res<Time>,
 ) {
-    for (entity, brick_state) in &mut brick_query {
-        // ensure that one loop has passed before despawning so physics will be applied
-        if brick_state.hit_bar == 0 && brick_state.hit_timer.elapsed_secs() > time.delta_seconds() as f32 {
-            commands.entity(entity).despawn();
-            game_state.bricks -= 1;
+    let touched: Vec<Entity> = brick_hit_event
+        .read()
+        .map(|event| event.ball)
+        .chain(paddle_hit_event.read().map(|event| event.ball))
+        .collect();
+    for (entity, mut linear_velocity, mut ball_state) in &mut ball_query {
+        if !ball_state.active || touched.contains(&entity) {
+            ball_state.contact_timer.reset();
+            continue;
+        }
+        ball_state.contact_timer.tick(time.delta());
+        let mut velocity = linear_velocity.0;
+        let mut nudged = false;
+        if ball_state.contact_timer.finished() {
+            // e.g. bouncing straight up and down between walls that never reach the paddle
+            ball_state.contact_timer.reset();
+            velocity = Vec2::from_angle(STUCK_NUDGE_ANGLE).rotate(velocity);
+            nudged = true;
+        }
+        if velocity.x != 0.0 {
+            ball_state.drift = velocity.x.signum();
+        }
+        let angle = velocity.y.abs().atan2(velocity.x.abs());
+        if nudged || !(MIN_BALL_ANGLE..=FRAC_PI_2 - MIN_BALL_ANGLE).contains(&angle) {
+            linear_velocity.0 = clamp_ball_angle(velocity, ball_state.drift, ball_state.speed, MIN_BALL_ANGLE);
         }
     }
 }
 
 fn game_won(
-    game_state: Res<GameState>,
+    mut game_state: ResMut<GameState>,
     mut game_won_event: EventWriter<GameWonEvent>,
+    mut start_demo_event: EventWriter<StartDemoEvent>,
+    mut recreate_bricks_event: EventWriter<RecreateBricksEvent>,
+    mut ball_query: Query<(&mut LinearVelocity, &mut Transform, &mut BallState), Without<PlayerRectangleState>>,
+    player_rectangle_query: Query<(&Transform, &PlayerRectangleState)>,
+    level: Res<Level>,
 ) {
-    if game_state.bricks == 0 {
-        game_won_event.send(GameWonEvent);
+    // leaving in_game makes sure the event is only sent once per game,
+    // an endless game only ends when the bricks reach the paddle or the lives run out,
+    // a versus game when one of the players runs out of lives
+    if game_state.in_game && game_state.bricks == 0 && !matches!(game_state.mode, GameMode::Endless | GameMode::Versus) {
+        // a cleared generated level is followed by the next one of the same seed
+        if game_state.seed.is_some() && game_state.level < GENERATED_LEVELS {
+            game_state.level += 1;
+            game_state.combo = 0;
+            let bricks = current_layout(&game_state, &level).bricks.len();
+            game_state.bricks = bricks as i32;
+            recreate_bricks_event.send(RecreateBricksEvent);
+            for (mut linear_velocity, mut transform, mut ball_state) in &mut ball_query {
+                let rectangle = serving_paddle(&player_rectangle_query, ball_state.server);
+                ball_state.active = false;
+                linear_velocity.0 = Vec2::ZERO;
+                let position = serve_position(&ball_state, rectangle.map(|(transform, _)| transform));
+                transform.translation.x = position.x;
+                transform.translation.y = position.y;
+            }
+            return;
+        }
+        game_state.in_game = false;
+        if game_state.demo {
+            start_demo_event.send(StartDemoEvent);
+        } else {
+            // before the event, so the high score table sees the final score
+            if game_state.mode == GameMode::TimeAttack {
+                let bonus = time_bonus(game_state.time_left);
+                game_state.score += bonus;
+            }
+            game_won_event.send(GameWonEvent);
+        }
     }
 }
 
 fn game_over(
-    game_state: Res<GameState>,
+    mut game_state: ResMut<GameState>,
     mut game_over_event: EventWriter<GameOverEvent>,
+    mut start_demo_event: EventWriter<StartDemoEvent>,
 ) {
 
-    if game_state.lives == 0 {
-        game_over_event.send(GameOverEvent);
+    if game_state.in_game && game_state.lives == 0 {
+        game_state.in_game = false;
+        if game_state.demo {
+            start_demo_event.send(StartDemoEvent);
+        } else {
+            game_over_event.send(GameOverEvent);
+        }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn reset_game_state(
     mut new_game_event: EventReader<NewGameEvent>,
+    mut start_demo_event: EventReader<StartDemoEvent>,
     mut recreate_bricks_event: EventWriter<RecreateBricksEvent>,
     mut hide_containers_event: EventWriter<HideContainersEvent>,
     mut game_state: ResMut<GameState>,
     mut ball_query: Query<(&mut LinearVelocity, &mut Transform, &mut BallState), Without<PlayerRectangleState>>,
-    mut player_rectangle_query: Query<&mut Transform, With<PlayerRectangleState>>,
+    mut player_rectangle_query: Query<(&mut Transform, &PlayerRectangleState)>,
+    mut physics_time: ResMut<Time<Physics>>,
+    level: Res<Level>,
+    coop_settings: Res<CoopSettings>,
+    difficulty: Res<DifficultySettings>,
 ) {
-    for _ in new_game_event.read() {
-        game_state.lives = 3;
+    let new_game = new_game_event.read().last();
+    let start_demo = start_demo_event.read().count() > 0;
+    if new_game.is_some() || start_demo {
         game_state.in_game = true;
-        game_state.bricks = 25;
+        // a real game always wins over a demo restart in the same frame
+        game_state.demo = new_game.is_none();
+        game_state.paused = false;
+        game_state.editing = false;
+        game_state.level = 1;
+        game_state.seed = new_game.and_then(|event| event.seed);
+        game_state.mode = new_game.map_or(GameMode::Classic, |event| event.mode);
+        game_state.rules = GameRules::for_new_game(new_game, &coop_settings, &difficulty);
+        game_state.score = 0;
+        game_state.player_scores = [0; 2];
+        let lives = game_state.rules.lives;
+        game_state.player_lives = [lives; 2];
+        game_state.lives = match game_state.mode {
+            GameMode::Coop if !game_state.rules.shared_lives => lives * 2,
+            GameMode::Versus => lives * 2,
+            _ => lives,
+        };
+        game_state.elapsed = 0.0;
+        game_state.time_left = TIME_ATTACK_START_SECS;
+        game_state.combo = 0;
+        let bricks = current_layout(&game_state, &level).bricks.len();
+        game_state.bricks = bricks as i32;
+        physics_time.unpause();
         let (mut linear_velocity, mut ball_transform, mut ball_state) = ball_query.single_mut();
-        let mut rectangle_transform = player_rectangle_query.single_mut();
         ball_state.active = false;
+        ball_state.speed = game_state.rules.ball_speed;
+        ball_state.last_player = 0;
+        ball_state.server = 0;
         linear_velocity.x = 0.0;
         linear_velocity.y = 0.0;
         ball_transform.translation.x = ball_state.initial_position.x;
         ball_transform.translation.y = ball_state.initial_position.y;
-        rectangle_transform.translation.x = 0.0;
+        for (mut rectangle_transform, rectangle_state) in &mut player_rectangle_query {
+            rectangle_transform.translation.x = (rectangle_state.min_x + rectangle_state.max_x) / 2.0;
+        }
         recreate_bricks_event.send(RecreateBricksEvent);
-        hide_containers_event.send(HideContainersEvent);
+        if new_game.is_some() {
+            hide_containers_event.send(HideContainersEvent);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEED: f32 = 800.0;
+
+    #[test]
+    fn deflect_keeps_speed() {
+        let deflection = PaddleDeflection::default();
+        for offset in [-0.7, -0.5, -0.1, 0.0, 0.3, 0.5, 0.9] {
+            for paddle_velocity in [-2000.0, 0.0, 500.0] {
+                let velocity = deflect(offset, paddle_velocity, SPEED, &deflection);
+                assert!((velocity.length() - SPEED).abs() < 0.01);
+                assert!(velocity.y > 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn deflect_angle_follows_offset() {
+        let deflection = PaddleDeflection { english: 0.0, ..default() };
+        let centre = deflect(0.0, 0.0, SPEED, &deflection);
+        assert!(centre.x.abs() < 0.01);
+        let left = deflect(-0.25, 0.0, SPEED, &deflection);
+        let right = deflect(0.25, 0.0, SPEED, &deflection);
+        assert!(left.x < 0.0 && right.x > 0.0);
+        assert!((left.x + right.x).abs() < 0.01);
+        // offsets past the edge bounce like the edge
+        let edge = deflect(0.5, 0.0, SPEED, &deflection);
+        assert_eq!(deflect(0.8, 0.0, SPEED, &deflection), edge);
+        assert!((edge.x.atan2(edge.y) - deflection.max_bounce_angle).abs() < 0.001);
+    }
+
+    #[test]
+    fn deflect_adds_english_and_keeps_min_vertical() {
+        let deflection = PaddleDeflection::default();
+        let still = deflect(0.0, 0.0, SPEED, &deflection);
+        let moving = deflect(0.0, 1000.0, SPEED, &deflection);
+        assert!(moving.x > still.x);
+        let flat = deflect(0.5, 100_000.0, SPEED, &deflection);
+        assert!(flat.y / SPEED >= deflection.min_vertical - 0.001);
+        assert_eq!(deflect(0.0, 0.0, 0.0, &deflection), Vec2::ZERO);
+    }
+
+    #[test]
+    fn clamp_ball_angle_lifts_a_flat_ball() {
+        let clamped = clamp_ball_angle(Vec2::new(-SPEED, 5.0), 1.0, SPEED, MIN_BALL_ANGLE);
+        assert!((clamped.length() - SPEED).abs() < 0.01);
+        assert!(clamped.x < 0.0 && clamped.y > 0.0);
+        assert!((clamped.y.atan2(-clamped.x) - MIN_BALL_ANGLE).abs() < 0.001);
+        // a ball with no vertical speed is sent down towards the paddle
+        assert!(clamp_ball_angle(Vec2::new(SPEED, 0.0), 1.0, SPEED, MIN_BALL_ANGLE).y < 0.0);
+    }
+
+    #[test]
+    fn clamp_ball_angle_turns_a_vertical_ball_with_the_drift() {
+        for drift in [1.0, -1.0] {
+            let clamped = clamp_ball_angle(Vec2::new(0.0, SPEED), drift, SPEED, MIN_BALL_ANGLE);
+            assert_eq!(clamped.x.signum(), drift);
+            assert!((clamped.x.abs().atan2(clamped.y) - MIN_BALL_ANGLE).abs() < 0.001);
+            assert!((clamped.length() - SPEED).abs() < 0.01);
+        }
+        // the ball's own direction wins over the drift
+        assert!(clamp_ball_angle(Vec2::new(1.0, -SPEED), -1.0, SPEED, MIN_BALL_ANGLE).x > 0.0);
+    }
+
+    #[test]
+    fn clamp_ball_angle_keeps_a_valid_direction() {
+        let velocity = Vec2::new(-0.6, -0.8) * SPEED;
+        assert!((clamp_ball_angle(velocity, 1.0, SPEED, MIN_BALL_ANGLE) - velocity).length() < 0.01);
+    }
+
+    #[test]
+    fn rules_from_the_network_stay_in_range() {
+        let rules = GameRules { shared_lives: false, ball_speed: 1e9, lives: -4 }.clamped();
+        assert!(!rules.shared_lives);
+        assert_eq!(rules.ball_speed, BALL_SPEED_RANGE.1);
+        assert_eq!(rules.lives, LIVES_RANGE.0);
+        let rules = GameRules { shared_lives: true, ball_speed: f32::NAN, lives: 100 }.clamped();
+        assert_eq!(rules.ball_speed, DifficultySettings::default().ball_speed);
+        assert_eq!(rules.lives, LIVES_RANGE.1);
+        let rules = GameRules { shared_lives: true, ball_speed: f32::NEG_INFINITY, lives: 3 };
+        assert_eq!(rules.clamped().ball_speed, BALL_SPEED_RANGE.0);
+        let rules = GameRules { shared_lives: true, ball_speed: 900.0, lives: 3 };
+        assert!(rules.clamped() == rules);
     }
 }
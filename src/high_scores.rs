@@ -0,0 +1,202 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::game_logic::{GameMode, GameOverEvent, GameState, GameWonEvent};
+use crate::ui::HighScoreLabel;
+
+const HIGH_SCORES_PATH: &str = "high_scores.ron";
+// entries kept per table
+const TABLE_SIZE: usize = 10;
+
+pub struct HighScoresPlugin;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct HighScore {
+    pub score: u32,
+    pub seconds: u32,
+}
+
+// one table per game mode, so endless scores don't crowd out the classic ones
+#[derive(Resource, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct HighScores {
+    pub classic: Vec<HighScore>,
+    pub endless: Vec<HighScore>,
+    pub time_attack: Vec<HighScore>,
+    pub coop: Vec<HighScore>,
+}
+
+impl HighScores {
+    pub fn table(&self, mode: GameMode) -> &[HighScore] {
+        match mode {
+            GameMode::Classic => &self.classic,
+            GameMode::Endless => &self.endless,
+            GameMode::TimeAttack => &self.time_attack,
+            GameMode::Coop => &self.coop,
+            // versus games are won against the other player, not for a score
+            GameMode::Versus => &[],
+        }
+    }
+
+    // the entry's place in its table, None if it didn't make it
+    fn record(&mut self, mode: GameMode, entry: HighScore) -> Option<usize> {
+        let table = match mode {
+            GameMode::Classic => &mut self.classic,
+            GameMode::Endless => &mut self.endless,
+            GameMode::TimeAttack => &mut self.time_attack,
+            GameMode::Coop => &mut self.coop,
+            GameMode::Versus => return None,
+        };
+        // the higher score wins, then the longer game for endless and the quicker one otherwise
+        let position = table
+            .iter()
+            .position(|other| match mode {
+                GameMode::Endless => (entry.score, entry.seconds) > (other.score, other.seconds),
+                GameMode::Classic | GameMode::TimeAttack | GameMode::Coop | GameMode::Versus => entry.score > other.score || (entry.score == other.score && entry.seconds < other.seconds),
+            })
+            .unwrap_or(table.len());
+        if position >= TABLE_SIZE {
+            return None;
+        }
+        table.insert(position, entry);
+        table.truncate(TABLE_SIZE);
+        Some(position)
+    }
+}
+
+// the entry of the game that just ended, highlighted in the table
+#[derive(Resource, Default)]
+struct LatestHighScore(Option<usize>);
+
+impl Plugin for HighScoresPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_high_scores())
+            .init_resource::<LatestHighScore>()
+            .add_systems(Update, (record_high_score, high_score_labels).chain());
+    }
+}
+
+fn load_high_scores() -> HighScores {
+    match fs::read_to_string(HIGH_SCORES_PATH) {
+        Ok(contents) => ron::from_str(&contents).unwrap_or_else(|error| {
+            warn!("invalid {}, starting with empty tables: {}", HIGH_SCORES_PATH, error);
+            HighScores::default()
+        }),
+        Err(_) => HighScores::default(),
+    }
+}
+
+fn record_high_score(
+    mut game_over_event: EventReader<GameOverEvent>,
+    mut game_won_event: EventReader<GameWonEvent>,
+    game_state: Res<GameState>,
+    mut high_scores: ResMut<HighScores>,
+    mut latest: ResMut<LatestHighScore>,
+) {
+    let ended = game_over_event.read().count() + game_won_event.read().count() > 0;
+    if !ended || game_state.demo {
+        return;
+    }
+    let entry = HighScore {
+        score: game_state.score,
+        seconds: game_state.elapsed as u32,
+    };
+    latest.0 = high_scores.record(game_state.mode, entry);
+    if latest.0.is_some() {
+        match ron::ser::to_string_pretty(&*high_scores, ron::ser::PrettyConfig::default()) {
+            Ok(contents) => {
+                if let Err(error) = fs::write(HIGH_SCORES_PATH, contents) {
+                    warn!("could not save {}: {}", HIGH_SCORES_PATH, error);
+                }
+            }
+            Err(error) => warn!("could not save {}: {}", HIGH_SCORES_PATH, error),
+        }
+    }
+}
+
+pub fn format_time(seconds: u32) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn high_score_labels(
+    game_state: Res<GameState>,
+    high_scores: Res<HighScores>,
+    latest: Res<LatestHighScore>,
+    mut label_query: Query<&mut Text, With<HighScoreLabel>>,
+) {
+    if !(high_scores.is_changed() || latest.is_changed()) {
+        return;
+    }
+    let title = match game_state.mode {
+        GameMode::Classic => "High scores",
+        GameMode::Endless => "Endless high scores",
+        GameMode::TimeAttack => "Time attack high scores",
+        GameMode::Coop => "Co-op high scores",
+        GameMode::Versus => {
+            for mut text in &mut label_query {
+                text.sections[0].value.clear();
+            }
+            return;
+        }
+    };
+    let mut value = format!("{}\n", title);
+    for (index, entry) in high_scores.table(game_state.mode).iter().enumerate() {
+        let marker = if latest.0 == Some(index) { ">" } else { " " };
+        value += &format!(
+            "{}{:>2}. {:>7}  {:>6}\n",
+            marker,
+            index + 1,
+            entry.score,
+            format_time(entry.seconds)
+        );
+    }
+    for mut text in &mut label_query {
+        text.sections[0].value = value.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(score: u32, seconds: u32) -> HighScore {
+        HighScore { score, seconds }
+    }
+
+    fn scores(table: &[HighScore]) -> Vec<(u32, u32)> {
+        table.iter().map(|entry| (entry.score, entry.seconds)).collect()
+    }
+
+    #[test]
+    fn record_orders_by_score_then_quicker_game() {
+        let mut high_scores = HighScores::default();
+        assert_eq!(high_scores.record(GameMode::Classic, entry(100, 60)), Some(0));
+        assert_eq!(high_scores.record(GameMode::Classic, entry(300, 90)), Some(0));
+        assert_eq!(high_scores.record(GameMode::Classic, entry(100, 30)), Some(1));
+        assert_eq!(high_scores.record(GameMode::Classic, entry(100, 60)), Some(3));
+        assert_eq!(scores(&high_scores.classic), [(300, 90), (100, 30), (100, 60), (100, 60)]);
+    }
+
+    #[test]
+    fn record_prefers_longer_endless_games() {
+        let mut high_scores = HighScores::default();
+        high_scores.record(GameMode::Endless, entry(100, 30));
+        assert_eq!(high_scores.record(GameMode::Endless, entry(100, 60)), Some(0));
+        assert_eq!(scores(&high_scores.endless), [(100, 60), (100, 30)]);
+        assert!(high_scores.classic.is_empty());
+    }
+
+    #[test]
+    fn record_keeps_the_table_size() {
+        let mut high_scores = HighScores::default();
+        for score in 1..=TABLE_SIZE as u32 {
+            high_scores.record(GameMode::TimeAttack, entry(score * 10, 0));
+        }
+        assert_eq!(high_scores.record(GameMode::TimeAttack, entry(5, 0)), None);
+        assert_eq!(high_scores.record(GameMode::TimeAttack, entry(55, 0)), Some(5));
+        assert_eq!(high_scores.time_attack.len(), TABLE_SIZE);
+        assert_eq!(high_scores.time_attack.last().map(|entry| entry.score), Some(20));
+        assert_eq!(high_scores.record(GameMode::Versus, entry(1000, 0)), None);
+    }
+}
@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::create_objects::PlayingField;
+
+// the layout played by the game and written by the editor
+pub const LEVEL_PATH: &str = "level.ron";
+
+// snap grid, column 0 is centered and row 0 sits a quarter of the field above the middle
+pub const MIN_COLUMN: i32 = -2;
+pub const MAX_COLUMN: i32 = 2;
+pub const MIN_ROW: i32 = -5;
+pub const MAX_ROW: i32 = 2;
+pub const MAX_HIT_POINTS: i32 = 5;
+
+pub struct LevelPlugin;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct BrickPlacement {
+    pub column: i32,
+    pub row: i32,
+    pub hit_points: i32,
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Level {
+    pub bricks: Vec<BrickPlacement>,
+}
+
+impl Level {
+    // the layout the game shipped with
+    pub fn classic() -> Self {
+        let mut bricks = Vec::new();
+        for row in -2..3 {
+            for column in -2..3 {
+                let hit_points = if (column % 2 == 0 && (row == 1 || row == -1)) || (column == 0 && row == 0) {
+                    3
+                } else {
+                    1
+                };
+                bricks.push(BrickPlacement { column, row, hit_points });
+            }
+        }
+        Level { bricks }
+    }
+
+    pub fn brick_at(&self, column: i32, row: i32) -> Option<usize> {
+        self.bricks
+            .iter()
+            .position(|brick| brick.column == column && brick.row == row)
+    }
+
+    pub fn load(path: &str) -> Result<Level, String> {
+        let contents = fs::read_to_string(path).map_err(|error| error.to_string())?;
+        let level: Level = ron::from_str(&contents).map_err(|error| error.to_string())?;
+        level.validate()?;
+        Ok(level)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| error.to_string())?;
+        fs::write(path, contents).map_err(|error| error.to_string())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (index, brick) in self.bricks.iter().enumerate() {
+            if !(MIN_COLUMN..=MAX_COLUMN).contains(&brick.column) || !(MIN_ROW..=MAX_ROW).contains(&brick.row) {
+                return Err(format!("brick at column {} row {} is outside the grid", brick.column, brick.row));
+            }
+            if !(1..=MAX_HIT_POINTS).contains(&brick.hit_points) {
+                return Err(format!(
+                    "brick at column {} row {} needs 1 to {} hit points",
+                    brick.column, brick.row, MAX_HIT_POINTS
+                ));
+            }
+            if self.brick_at(brick.column, brick.row) != Some(index) {
+                return Err(format!("more than one brick at column {} row {}", brick.column, brick.row));
+            }
+        }
+        Ok(())
+    }
+}
+
+// brick sizes and positions on the playing field
+pub struct LevelGrid {
+    pub brick_width: f32,
+    pub brick_height: f32,
+    cell_width: f32,
+    cell_height: f32,
+    origin_y: f32,
+}
+
+impl LevelGrid {
+    pub fn new(field: &PlayingField) -> Self {
+        let brick_width = field.width / 8.;
+        let brick_height = field.height / 20.;
+        LevelGrid {
+            brick_width,
+            brick_height,
+            cell_width: brick_width + field.width / 40.,
+            cell_height: brick_height + field.height / 30.,
+            origin_y: field.height / 4.0,
+        }
+    }
+
+    pub fn position(&self, column: i32, row: i32) -> Vec2 {
+        Vec2::new(column as f32 * self.cell_width, row as f32 * self.cell_height + self.origin_y)
+    }
+
+    // vertical distance between two rows
+    pub fn row_spacing(&self) -> f32 {
+        self.cell_height
+    }
+
+    // the row as far below the middle of the field as row is above it,
+    // row 0 is three rows above the middle for any field size
+    pub fn mirrored_row(row: i32) -> i32 {
+        -row - 6
+    }
+
+    // the grid cell under a world position, if any
+    pub fn cell_at(&self, position: Vec2) -> Option<(i32, i32)> {
+        let column = (position.x / self.cell_width).round() as i32;
+        let row = ((position.y - self.origin_y) / self.cell_height).round() as i32;
+        ((MIN_COLUMN..=MAX_COLUMN).contains(&column) && (MIN_ROW..=MAX_ROW).contains(&row)).then_some((column, row))
+    }
+}
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_level());
+    }
+}
+
+fn load_level() -> Level {
+    if fs::metadata(LEVEL_PATH).is_err() {
+        return Level::classic();
+    }
+    Level::load(LEVEL_PATH).unwrap_or_else(|error| {
+        warn!("invalid {}, using the built-in level: {}", LEVEL_PATH, error);
+        Level::classic()
+    })
+}
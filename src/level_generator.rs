@@ -0,0 +1,267 @@
+use std::borrow::Cow;
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::game_logic::{GameMode, GameState};
+use crate::level::{BrickPlacement, Level, LevelGrid, MAX_COLUMN, MAX_HIT_POINTS, MAX_ROW, MIN_COLUMN};
+
+// lowest row the generator fills, the rows below are left free for the ball
+const MIN_GENERATED_ROW: i32 = -3;
+
+// the same seed always gives the same layout, so the generator carries its own
+// small random number generator instead of depending on one that may change
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // uniform in [0, 1)
+    fn next_f32(&mut self) -> f32 {
+        (self.next() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn below(&mut self, bound: u32) -> u32 {
+        (self.next() % bound.max(1) as u64) as u32
+    }
+}
+
+enum Pattern {
+    // one half filled at random and mirrored onto the other
+    Symmetric,
+    // mirrored blobs where smooth noise is below the density
+    Clusters,
+    // every other row, with gaps that shift from one stripe to the next
+    Stripes,
+}
+
+// BRICK_BREAKER_SEED=1234 replays a shared level, otherwise every game gets a new seed
+pub fn pick_seed() -> u32 {
+    env::var("BRICK_BREAKER_SEED")
+        .ok()
+        .and_then(|seed| seed.trim().parse().ok())
+        .unwrap_or_else(random_seed)
+}
+
+fn random_seed() -> u32 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos() as u64);
+    (SplitMix64(nanos).next() % 1_000_000) as u32
+}
+
+// a generated game is won after clearing this many levels, every one tougher than the last
+pub const GENERATED_LEVELS: u32 = 5;
+
+// rows an endless game starts with, the rest come down during the game
+pub const ENDLESS_START_ROWS: u32 = 3;
+
+// rows of the top player's wall in a versus game, far enough from the goal line
+// for the ball to fit between, the bottom player gets the same wall mirrored
+const VERSUS_ROWS: [i32; 2] = [0, 1];
+
+// the generated layout for a seeded, endless or versus game, the loaded or edited level otherwise
+pub fn current_layout<'a>(game_state: &GameState, level: &'a Level) -> Cow<'a, Level> {
+    match (game_state.mode, game_state.seed) {
+        (GameMode::Endless, seed) => {
+            let seed = seed.unwrap_or_default();
+            let mut bricks = Vec::new();
+            for index in 0..ENDLESS_START_ROWS {
+                // new rows come in at the top, so the first row is the lowest
+                let row = MAX_ROW - (ENDLESS_START_ROWS - 1 - index) as i32;
+                bricks.extend(generate_row(seed, index, row));
+            }
+            Cow::Owned(Level { bricks })
+        }
+        (GameMode::Classic | GameMode::TimeAttack | GameMode::Coop, Some(seed)) => Cow::Owned(generate(seed, game_state.level)),
+        (GameMode::Classic | GameMode::TimeAttack | GameMode::Coop, None) => Cow::Borrowed(level),
+        (GameMode::Versus, seed) => Cow::Owned(versus_layout(seed)),
+    }
+}
+
+// a wall of bricks in front of each goal line, the same for both players
+fn versus_layout(seed: Option<u32>) -> Level {
+    let mut bricks = Vec::new();
+    for (index, row) in VERSUS_ROWS.into_iter().enumerate() {
+        match seed {
+            Some(seed) => bricks.extend(generate_row(seed, index as u32, row)),
+            // the row nearer the goal line takes more hits
+            None => bricks.extend((MIN_COLUMN..=MAX_COLUMN).map(|column| BrickPlacement {
+                column,
+                row,
+                hit_points: 1 + index as i32,
+            })),
+        }
+    }
+    let mirrored: Vec<BrickPlacement> = bricks
+        .iter()
+        .map(|brick| BrickPlacement {
+            row: LevelGrid::mirrored_row(brick.row),
+            ..brick.clone()
+        })
+        .collect();
+    bricks.extend(mirrored);
+    Level { bricks }
+}
+
+// one row of the endless mode, index counts the rows of the game so far
+pub fn generate_row(seed: u32, index: u32, row: i32) -> Vec<BrickPlacement> {
+    let mut rng = SplitMix64((seed as u64) << 32 | index as u64);
+    // gets tougher every few rows
+    let difficulty = 1 + index / 5;
+    let max_hit_points = (difficulty as i32).min(MAX_HIT_POINTS);
+    let density = (0.5 + 0.04 * difficulty as f32).min(0.9);
+    let mut bricks = Vec::new();
+    for column in MIN_COLUMN..=MAX_COLUMN {
+        if rng.next_f32() < density {
+            bricks.push(BrickPlacement {
+                column,
+                row,
+                hit_points: 1 + rng.below(max_hit_points as u32) as i32,
+            });
+        }
+    }
+    if bricks.is_empty() {
+        let column = MIN_COLUMN + rng.below((MAX_COLUMN - MIN_COLUMN + 1) as u32) as i32;
+        bricks.push(BrickPlacement { column, row, hit_points: 1 });
+    }
+    bricks
+}
+
+fn hash(seed: u64, x: i32, y: i32) -> f32 {
+    let mut rng = SplitMix64(seed ^ ((x as u64) << 32) ^ (y as u32 as u64));
+    rng.next_f32()
+}
+
+// value noise, smoothly interpolated between random values on a coarse lattice
+fn noise(seed: u64, x: f32, y: f32) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (tx, ty) = (x - x0, y - y0);
+    let (sx, sy) = (tx * tx * (3.0 - 2.0 * tx), ty * ty * (3.0 - 2.0 * ty));
+    let (ix, iy) = (x0 as i32, y0 as i32);
+    let top = hash(seed, ix, iy) * (1.0 - sx) + hash(seed, ix + 1, iy) * sx;
+    let bottom = hash(seed, ix, iy + 1) * (1.0 - sx) + hash(seed, ix + 1, iy + 1) * sx;
+    top * (1.0 - sy) + bottom * sy
+}
+
+// difficulty starts at 1, higher values give tougher and more bricks
+pub fn generate(seed: u32, difficulty: u32) -> Level {
+    let mut rng = SplitMix64(seed as u64);
+    let difficulty = difficulty.max(1);
+    let pattern = match rng.below(3) {
+        0 => Pattern::Symmetric,
+        1 => Pattern::Clusters,
+        _ => Pattern::Stripes,
+    };
+    let density = 0.45 + 0.05 * difficulty.min(6) as f32 + 0.2 * rng.next_f32();
+    let noise_seed = rng.next();
+    let noise_scale = 0.5 + 0.3 * rng.next_f32();
+    let stripe_gap = 2 + rng.below(2) as i32;
+    let mut cells = Vec::new();
+    for row in MIN_GENERATED_ROW..=MAX_ROW {
+        for column in MIN_COLUMN..=MAX_COLUMN {
+            let filled = match pattern {
+                Pattern::Symmetric => {
+                    // decide once per mirrored pair, the right half copies the left
+                    column <= 0 && rng.next_f32() < density
+                }
+                Pattern::Clusters => {
+                    noise(noise_seed, column.abs() as f32 * noise_scale, row as f32 * noise_scale) < density
+                }
+                Pattern::Stripes => {
+                    let stripe = (row - MIN_GENERATED_ROW) / 2;
+                    (row - MIN_GENERATED_ROW) % 2 == 0 && (column + stripe).rem_euclid(stripe_gap) != 0
+                }
+            };
+            if filled {
+                cells.push((column, row));
+            }
+        }
+    }
+    if let Pattern::Symmetric = pattern {
+        let mirrored: Vec<(i32, i32)> = cells
+            .iter()
+            .filter(|(column, _)| *column < 0)
+            .map(|(column, row)| (-column, *row))
+            .collect();
+        cells.extend(mirrored);
+    }
+    if cells.is_empty() {
+        cells.push((0, 0));
+    }
+    // the toughest regular bricks get closer to the limit with difficulty,
+    // the one left for special bricks
+    let max_regular = (1 + difficulty as i32).min(MAX_HIT_POINTS - 1);
+    let rows = (MAX_ROW - MIN_GENERATED_ROW) as f32;
+    let mut bricks: Vec<BrickPlacement> = cells
+        .into_iter()
+        .map(|(column, row)| {
+            // higher rows are tougher, with a little jitter
+            let height = (row - MIN_GENERATED_ROW) as f32 / rows;
+            let jitter = rng.next_f32() * 0.8 - 0.4;
+            let hit_points = (1.0 + height * (max_regular - 1) as f32 + jitter).round() as i32;
+            BrickPlacement {
+                column,
+                row,
+                hit_points: hit_points.clamp(1, max_regular),
+            }
+        })
+        .collect();
+    // a few special bricks with the most hit points, mirrored like the layout
+    let specials = rng.below(difficulty.min(3) + 1);
+    for _ in 0..specials {
+        let index = rng.below(bricks.len() as u32) as usize;
+        let (column, row) = (bricks[index].column, bricks[index].row);
+        for brick in bricks.iter_mut() {
+            if brick.row == row && brick.column.abs() == column.abs() {
+                brick.hit_points = MAX_HIT_POINTS;
+            }
+        }
+    }
+    bricks.sort_by_key(|brick| (brick.row, brick.column));
+    Level { bricks }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn in_bounds(brick: &BrickPlacement) -> bool {
+        (MIN_COLUMN..=MAX_COLUMN).contains(&brick.column)
+            && (MIN_GENERATED_ROW..=MAX_ROW).contains(&brick.row)
+            && (1..=MAX_HIT_POINTS).contains(&brick.hit_points)
+    }
+
+    #[test]
+    fn generate_is_deterministic() {
+        for seed in [0, 1, 42, 123_456, u32::MAX] {
+            for difficulty in [0, 1, 5] {
+                let level = generate(seed, difficulty);
+                assert!(level.bricks == generate(seed, difficulty).bricks);
+                assert!(!level.bricks.is_empty());
+                assert!(level.bricks.iter().all(in_bounds));
+            }
+        }
+        let first = generate(1, 1);
+        assert!((2..20).any(|seed| generate(seed, 1).bricks != first.bricks));
+    }
+
+    #[test]
+    fn generate_row_is_deterministic() {
+        for seed in [0, 7, u32::MAX] {
+            for index in [0, 1, 30] {
+                let row = generate_row(seed, index, MAX_ROW);
+                assert!(row == generate_row(seed, index, MAX_ROW));
+                assert!(!row.is_empty());
+                assert!(row.iter().all(|brick| brick.row == MAX_ROW && in_bounds(brick)));
+            }
+        }
+        let first = generate_row(7, 0, MAX_ROW);
+        assert!((1..20).any(|index| generate_row(7, index, MAX_ROW) != first));
+    }
+}
@@ -2,16 +2,62 @@ use bevy::prelude::*;
 use bevy_xpbd_2d::prelude::*;
 use bevy_embedded_assets::EmbeddedAssetPlugin;
 
+mod autopilot;
+mod brick_damage;
+mod brick_health;
+mod coop;
+mod endless;
 mod game_logic;
+mod high_scores;
+mod music;
+mod network;
+mod particles;
+mod save_game;
+mod screen_effects;
+mod settings;
+mod sound;
+mod spectator;
+mod textures;
+mod theme;
+mod time_attack;
+mod trail;
 mod ui;
+mod versus;
 mod create_objects;
+mod display;
+mod editor;
+mod level;
+mod level_generator;
 
+use crate::autopilot::AutopilotPlugin;
+use crate::brick_damage::BrickDamagePlugin;
+use crate::brick_health::BrickHealthPlugin;
+use crate::coop::CoopPlugin;
+use crate::endless::EndlessPlugin;
 use crate::game_logic::GameLogicPlugin;
+use crate::high_scores::HighScoresPlugin;
+use crate::music::MusicPlugin;
+use crate::network::NetworkPlugin;
+use crate::particles::ParticlesPlugin;
+use crate::save_game::SaveGamePlugin;
+use crate::screen_effects::ScreenEffectsPlugin;
+use crate::settings::SettingsPlugin;
+use crate::sound::SoundPlugin;
+use crate::spectator::SpectatorPlugin;
+use crate::textures::TexturesPlugin;
+use crate::theme::ThemePlugin;
+use crate::time_attack::TimeAttackPlugin;
+use crate::trail::TrailPlugin;
 use crate::ui::UiPlugin;
+use crate::versus::VersusPlugin;
 use crate::create_objects::CreateObjectsPlugin;
+use crate::display::DisplayPlugin;
+use crate::editor::EditorPlugin;
+use crate::level::LevelPlugin;
 
 fn main() {
     App::new()
-        .add_plugins((DefaultPlugins, EmbeddedAssetPlugin::default(), PhysicsPlugins::default(), CreateObjectsPlugin, GameLogicPlugin, UiPlugin))
+        .add_plugins((DefaultPlugins, EmbeddedAssetPlugin::default(), PhysicsPlugins::default(), CreateObjectsPlugin, GameLogicPlugin, UiPlugin, AutopilotPlugin, LevelPlugin, EditorPlugin, NetworkPlugin, SpectatorPlugin, SaveGamePlugin, DisplayPlugin))
+        .add_plugins((SettingsPlugin, ThemePlugin, SoundPlugin, MusicPlugin, ParticlesPlugin, ScreenEffectsPlugin, TrailPlugin, BrickHealthPlugin, TexturesPlugin, BrickDamagePlugin, EndlessPlugin, HighScoresPlugin, TimeAttackPlugin, CoopPlugin, VersusPlugin))
         .run();
 }
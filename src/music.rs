@@ -0,0 +1,145 @@
+use bevy::audio::Volume;
+use bevy::prelude::*;
+
+use crate::game_logic::{GameMode, GameOverEvent, GameState, GameWonEvent};
+use crate::sound::AudioSettings;
+
+pub struct MusicPlugin;
+
+const CROSSFADE_SECS: f32 = 1.5;
+// share of the music volume kept while the game is paused
+const PAUSE_DUCKING: f32 = 0.3;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MusicScreen {
+    Menu,
+    Game(GameMode),
+    Won,
+    GameOver,
+}
+
+#[derive(Resource)]
+struct MusicPlaylist {
+    menu: Handle<AudioSource>,
+    // the classic and co-op levels
+    level: Handle<AudioSource>,
+    // the modes racing against rows, the clock or the other player
+    fast_level: Handle<AudioSource>,
+    won: Handle<AudioSource>,
+    game_over: Handle<AudioSource>,
+}
+
+impl MusicPlaylist {
+    fn track(&self, screen: MusicScreen) -> Handle<AudioSource> {
+        match screen {
+            MusicScreen::Menu => self.menu.clone(),
+            MusicScreen::Game(GameMode::Classic | GameMode::Coop) => self.level.clone(),
+            MusicScreen::Game(GameMode::Endless | GameMode::TimeAttack | GameMode::Versus) => self.fast_level.clone(),
+            MusicScreen::Won => self.won.clone(),
+            MusicScreen::GameOver => self.game_over.clone(),
+        }
+    }
+}
+
+#[derive(Component)]
+struct MusicTrack {
+    screen: MusicScreen,
+    // crossfade factor between 0 and 1
+    fade: f32,
+    fading_out: bool,
+}
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup)
+            .add_systems(Update, (select_music, fade_music).chain());
+    }
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(MusicPlaylist {
+        menu: asset_server.load("embedded://music/menu.wav"),
+        level: asset_server.load("embedded://music/level_1.wav"),
+        fast_level: asset_server.load("embedded://music/level_2.wav"),
+        won: asset_server.load("embedded://music/won.wav"),
+        game_over: asset_server.load("embedded://music/game_over.wav"),
+    });
+}
+
+// won tells the screen after a game, as the game state doesn't keep how it ended
+pub fn music_screen(game_state: &GameState, won: bool) -> MusicScreen {
+    if game_state.demo || game_state.editing {
+        MusicScreen::Menu
+    } else if game_state.in_game {
+        MusicScreen::Game(game_state.mode)
+    } else if won {
+        MusicScreen::Won
+    } else {
+        MusicScreen::GameOver
+    }
+}
+
+fn select_music(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    playlist: Res<MusicPlaylist>,
+    mut track_query: Query<&mut MusicTrack>,
+    mut game_won_event: EventReader<GameWonEvent>,
+    mut game_over_event: EventReader<GameOverEvent>,
+    mut won: Local<bool>,
+) {
+    if game_won_event.read().count() > 0 {
+        *won = true;
+    }
+    if game_over_event.read().count() > 0 {
+        *won = false;
+    }
+    let screen = music_screen(&game_state, *won);
+    let mut playing = false;
+    for mut track in &mut track_query {
+        if track.screen == screen && !track.fading_out {
+            playing = true;
+        } else {
+            track.fading_out = true;
+        }
+    }
+    if !playing {
+        commands.spawn((
+            AudioBundle {
+                source: playlist.track(screen),
+                settings: PlaybackSettings::LOOP.with_volume(Volume::new(0.0)),
+            },
+            MusicTrack {
+                screen,
+                fade: 0.0,
+                fading_out: false,
+            },
+        ));
+    }
+}
+
+fn fade_music(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    audio_settings: Res<AudioSettings>,
+    mut track_query: Query<(Entity, &mut MusicTrack, Option<&AudioSink>)>,
+    time: Res<Time>,
+) {
+    let step = time.delta_seconds() / CROSSFADE_SECS;
+    let ducking = if game_state.paused { PAUSE_DUCKING } else { 1.0 };
+    for (entity, mut track, sink) in &mut track_query {
+        if track.fading_out {
+            track.fade = (track.fade - step).max(0.0);
+            if track.fade == 0.0 {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        } else {
+            track.fade = (track.fade + step).min(1.0);
+        }
+        // the sink only exists once the source has loaded
+        if let Some(sink) = sink {
+            sink.set_volume(track.fade * ducking * audio_settings.music());
+        }
+    }
+}
@@ -0,0 +1,769 @@
+use bevy::prelude::*;
+use bevy::time::{TimeSystem, TimeUpdateStrategy};
+use bevy_xpbd_2d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+use crate::coop::CoopSettings;
+use crate::create_objects::{BallState, BrickState, PlayerRectangleState, PlayingField};
+use crate::game_logic::{
+    ControlSettings, DifficultySettings, GameMode, GameOverEvent, GameRules, GameState, GameWonEvent, PaddleInput, PlayerInputs,
+    StartDemoEvent,
+};
+use crate::ui::NewGameEvent;
+
+// co-op and versus games between two instances over UDP, in lockstep: a frame of the game
+// only runs once the inputs of both players for it are known, and every frame is the same
+// fixed time step, so both sides compute the same game from the same inputs
+//
+// BRICK_BREAKER_HOST=127.0.0.1:7000 waits for a player on that address,
+// BRICK_BREAKER_JOIN=127.0.0.1:7000 joins them, the host picks the game on the start screen
+pub struct NetworkPlugin;
+
+const HOST_VAR: &str = "BRICK_BREAKER_HOST";
+const JOIN_VAR: &str = "BRICK_BREAKER_JOIN";
+
+// frames between sampling an input and playing it, covers the round trip on a local network
+const INPUT_DELAY: u32 = 3;
+// the latest inputs go out in every packet, so a lost packet doesn't stall the game
+const RESENT_INPUTS: usize = 8;
+// the other side can't have sampled inputs further ahead of this side's frame,
+// later frames in a packet are rejected
+const INPUT_WINDOW: u32 = INPUT_DELAY + RESENT_INPUTS as u32;
+// frames between two comparisons of the game state
+const CHECKSUM_INTERVAL: u32 = 30;
+// checksums older than this were compared already or their counterpart got lost
+const CHECKSUM_WINDOW: u32 = 2 * CHECKSUM_INTERVAL;
+const FRAME_SECS: f64 = 1.0 / 60.0;
+// frames held after a new game starts, so the reset is done on both sides before the first one
+const HOST_START_FRAMES: u32 = 3;
+const GUEST_START_FRAMES: u32 = 2;
+// the other side is gone after this long without a packet
+const TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_PACKET_SIZE: usize = 2048;
+
+#[derive(Serialize, Deserialize)]
+enum Message {
+    // the guest knocking, between games it doubles as a keep-alive
+    Hello,
+    Welcome,
+    // sent by the host until the guest's first inputs for the game arrive
+    Start {
+        game: u32,
+        mode: GameMode,
+        seed: Option<u32>,
+        rules: GameRules,
+        // both sides have to simulate the same field
+        field: PlayingField,
+    },
+    // the sender's inputs from frame first on, and the checksum after a past frame
+    Inputs {
+        game: u32,
+        first: u32,
+        inputs: Vec<PaddleInput>,
+        checksum: Option<(u32, u64)>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Role {
+    // player 1, picks the games
+    Host,
+    // player 2
+    Guest,
+}
+
+impl Role {
+    fn player(self) -> usize {
+        match self {
+            Role::Host => 0,
+            Role::Guest => 1,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Session {
+    game: u32,
+    // the game the host started, until the guest has it too
+    mode: GameMode,
+    seed: Option<u32>,
+    rules: GameRules,
+    active: bool,
+    // frames to hold before the first one
+    hold: u32,
+    // frames played so far
+    frame: u32,
+    // the latest local inputs, the first of them for frame local_first
+    local_first: u32,
+    local_inputs: VecDeque<PaddleInput>,
+    // the remote inputs known from the current frame on
+    remote_inputs: VecDeque<Option<PaddleInput>>,
+    // whether the game moves on this frame, decided before the frame's time is updated
+    playing: bool,
+    // a launch pressed while waiting for the other side, kept for the next sampled input
+    launch_pressed: bool,
+    local_checksums: HashMap<u32, u64>,
+    remote_checksums: HashMap<u32, u64>,
+    latest_checksum: Option<(u32, u64)>,
+    // host only, the guest has sent inputs for this game
+    peer_started: bool,
+    // the first frame the two sides disagreed on
+    desync: Option<u32>,
+}
+
+impl Session {
+    fn new(game: u32, mode: GameMode, seed: Option<u32>, rules: GameRules, hold: u32) -> Self {
+        // nobody moves during the first frames, until the delayed inputs come in
+        let waiting = INPUT_DELAY as usize;
+        Session {
+            game,
+            mode,
+            seed,
+            rules,
+            active: true,
+            hold,
+            local_inputs: VecDeque::from(vec![PaddleInput::default(); waiting]),
+            remote_inputs: VecDeque::from(vec![Some(PaddleInput::default()); waiting]),
+            ..default()
+        }
+    }
+
+    // the frame after the last local input
+    fn local_end(&self) -> u32 {
+        self.local_first + self.local_inputs.len() as u32
+    }
+
+    fn add_remote_inputs(&mut self, first: u32, inputs: Vec<PaddleInput>) {
+        for (index, input) in inputs.into_iter().enumerate() {
+            let Some(frame) = u32::try_from(index).ok().and_then(|index| first.checked_add(index)) else {
+                break;
+            };
+            // frames already played are resent, frames too far ahead can't be genuine
+            if frame < self.frame || frame >= self.frame + INPUT_WINDOW {
+                continue;
+            }
+            let index = (frame - self.frame) as usize;
+            if self.remote_inputs.len() <= index {
+                self.remote_inputs.resize(index + 1, None);
+            }
+            self.remote_inputs[index] = Some(input);
+        }
+    }
+
+    fn add_remote_checksum(&mut self, frame: u32, checksum: u64) {
+        if frame.saturating_add(CHECKSUM_WINDOW) >= self.frame && frame < self.frame + INPUT_WINDOW {
+            self.remote_checksums.insert(frame, checksum);
+        }
+    }
+
+    // plays the current frame once both inputs for it are known, the local and the remote one,
+    // the input sampled now is played INPUT_DELAY frames later
+    fn advance(&mut self, mut input: PaddleInput) -> Option<(PaddleInput, PaddleInput)> {
+        self.launch_pressed |= input.launch;
+        self.playing = false;
+        if self.hold > 0 {
+            self.hold -= 1;
+            return None;
+        }
+        if self.local_end() == self.frame + INPUT_DELAY {
+            input.launch = self.launch_pressed;
+            self.launch_pressed = false;
+            self.local_inputs.push_back(input);
+        }
+        let remote = self.remote_inputs.front().copied().flatten()?;
+        let local = self.local_inputs[(self.frame - self.local_first) as usize];
+        self.remote_inputs.pop_front();
+        self.frame += 1;
+        self.playing = true;
+        // only the latest local inputs are still resent, the current frame's is among them
+        while self.local_inputs.len() > RESENT_INPUTS {
+            self.local_inputs.pop_front();
+            self.local_first += 1;
+        }
+        let oldest = self.frame.saturating_sub(CHECKSUM_WINDOW);
+        self.local_checksums.retain(|frame, _| *frame >= oldest);
+        self.remote_checksums.retain(|frame, _| *frame >= oldest);
+        Some((local, remote))
+    }
+}
+
+#[derive(Resource)]
+struct Network {
+    socket: UdpSocket,
+    role: Role,
+    // the host learns the guest's address from their first packet
+    peer: Option<SocketAddr>,
+    connected: bool,
+    last_heard: Instant,
+    session: Session,
+    // why this side stopped talking to the other one, for good
+    refused: Option<String>,
+}
+
+impl Network {
+    fn send(&self, message: &Message) {
+        let Some(peer) = self.peer else {
+            return;
+        };
+        match ron::to_string(message) {
+            Ok(contents) => {
+                if let Err(error) = self.socket.send_to(contents.as_bytes(), peer) {
+                    if error.kind() != ErrorKind::WouldBlock {
+                        warn!("could not send to {}: {}", peer, error);
+                    }
+                }
+            }
+            Err(error) => warn!("could not serialize a network message: {}", error),
+        }
+    }
+}
+
+#[derive(Component)]
+struct NetworkStatus;
+
+impl Plugin for NetworkPlugin {
+    fn build(&self, app: &mut App) {
+        let Some(network) = open_network() else {
+            return;
+        };
+        app.insert_resource(network)
+            .add_systems(Startup, setup)
+            // before the time update, so a frame that has to wait for the other side doesn't pass any time
+            .add_systems(First, (receive_messages, advance_frame, send_messages).chain().before(TimeSystem))
+            .add_systems(
+                PostUpdate,
+                (start_and_end_sessions, hold_physics)
+                    .chain()
+                    .before(PhysicsSet::StepSimulation),
+            )
+            .add_systems(Update, network_status);
+    }
+}
+
+fn open_network() -> Option<Network> {
+    let (role, address) = match (env::var(HOST_VAR), env::var(JOIN_VAR)) {
+        (Ok(address), _) => (Role::Host, address),
+        (Err(_), Ok(address)) => (Role::Guest, address),
+        _ => return None,
+    };
+    let address: SocketAddr = match address.trim().parse() {
+        Ok(address) => address,
+        Err(error) => {
+            warn!("invalid network address {}, playing offline: {}", address, error);
+            return None;
+        }
+    };
+    let (bind, peer) = match role {
+        Role::Host => (address, None),
+        // any free port, the host answers to wherever the packets come from
+        Role::Guest => {
+            let any: IpAddr = if address.is_ipv4() { Ipv4Addr::UNSPECIFIED.into() } else { Ipv6Addr::UNSPECIFIED.into() };
+            (SocketAddr::new(any, 0), Some(address))
+        }
+    };
+    let socket = match UdpSocket::bind(bind).and_then(|socket| socket.set_nonblocking(true).map(|_| socket)) {
+        Ok(socket) => socket,
+        Err(error) => {
+            warn!("could not open a socket on {}, playing offline: {}", bind, error);
+            return None;
+        }
+    };
+    Some(Network {
+        socket,
+        role,
+        peer,
+        connected: false,
+        last_heard: Instant::now(),
+        session: Session::default(),
+        refused: None,
+    })
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("embedded://fonts/AgaveNerdFont-Regular.ttf"),
+                font_size: 20.0,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            right: Val::Px(5.0),
+            ..default()
+        }),
+        Label,
+        NetworkStatus,
+    ));
+}
+
+fn receive_messages(
+    mut network: ResMut<Network>,
+    mut new_game_event: EventWriter<NewGameEvent>,
+    mut game_state: ResMut<GameState>,
+    mut physics_time: ResMut<Time<Physics>>,
+    mut time_update_strategy: ResMut<TimeUpdateStrategy>,
+    mut start_demo_event: EventWriter<StartDemoEvent>,
+    field: Res<PlayingField>,
+) {
+    if network.refused.is_some() {
+        return;
+    }
+    let mut buffer = [0; MAX_PACKET_SIZE];
+    loop {
+        let (size, from) = match network.socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(error) => {
+                if error.kind() != ErrorKind::WouldBlock {
+                    warn!("could not receive: {}", error);
+                }
+                break;
+            }
+        };
+        // the host plays with the first guest that knocks, the guest only listens to the host
+        if network.peer.is_some_and(|peer| peer != from) {
+            continue;
+        }
+        let message: Message = match std::str::from_utf8(&buffer[..size])
+            .map_err(|error| error.to_string())
+            .and_then(|contents| ron::from_str(contents).map_err(|error| error.to_string()))
+        {
+            Ok(message) => message,
+            Err(error) => {
+                warn!("invalid packet from {}: {}", from, error);
+                continue;
+            }
+        };
+        if !network.connected {
+            info!("connected to {}", from);
+        }
+        network.peer = Some(from);
+        network.connected = true;
+        network.last_heard = Instant::now();
+        match message {
+            Message::Hello => {
+                if network.role == Role::Host {
+                    network.send(&Message::Welcome);
+                }
+            }
+            Message::Welcome => {}
+            Message::Start { game, mode, seed, rules, field: host_field } => {
+                if network.role == Role::Guest && game > network.session.game {
+                    if let Err(error) = check_field(&host_field, &field) {
+                        error!("refusing to play with {}: {}", from, error);
+                        network.connected = false;
+                        network.refused = Some(error);
+                        return;
+                    }
+                    // the host's rules apply to this game only, the guest's settings stay as they are
+                    let rules = rules.clamped();
+                    network.session = Session::new(game, mode, seed, rules, GUEST_START_FRAMES);
+                    begin_online_game(&mut game_state, &mut physics_time);
+                    new_game_event.send(NewGameEvent { seed, mode, rules: Some(rules) });
+                }
+            }
+            Message::Inputs { game, first, inputs, checksum } => {
+                let session = &mut network.session;
+                if game != session.game || !session.active {
+                    continue;
+                }
+                session.peer_started = true;
+                session.add_remote_inputs(first, inputs);
+                if let Some((frame, checksum)) = checksum {
+                    session.add_remote_checksum(frame, checksum);
+                }
+                compare_checksums(session);
+            }
+        }
+    }
+    if network.connected && network.last_heard.elapsed() > TIMEOUT {
+        warn!("lost the connection to the other player");
+        network.connected = false;
+        if network.role == Role::Host {
+            network.peer = None;
+        }
+        if network.session.active {
+            network.session.active = false;
+            end_online_game(&mut game_state, &mut physics_time, &mut time_update_strategy);
+            start_demo_event.send(StartDemoEvent);
+        }
+    }
+}
+
+// a game between different fields would go out of sync with the first bounce
+fn check_field(host: &PlayingField, local: &PlayingField) -> Result<(), String> {
+    if host == local {
+        return Ok(());
+    }
+    Err(format!(
+        "the host plays on a {}x{} field, this side on {}x{}",
+        host.width, host.height, local.width, local.height
+    ))
+}
+
+fn compare_checksums(session: &mut Session) {
+    let frames: Vec<u32> = session
+        .remote_checksums
+        .keys()
+        .filter(|frame| session.local_checksums.contains_key(frame))
+        .copied()
+        .collect();
+    for frame in frames {
+        let local = session.local_checksums.remove(&frame);
+        let remote = session.remote_checksums.remove(&frame);
+        if local != remote && session.desync.is_none() {
+            error!("the game is out of sync since frame {}", frame);
+            session.desync = Some(frame);
+        }
+    }
+}
+
+// everything the two sides have to agree on: the score, the lives, the ball, the paddles and the bricks
+fn checksum(
+    game_state: &GameState,
+    ball_query: &Query<(&Transform, &LinearVelocity, &BallState)>,
+    rectangle_query: &Query<(&Transform, &PlayerRectangleState)>,
+    brick_query: &Query<(&Transform, &BrickState)>,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (game_state.lives, game_state.score, game_state.player_lives, game_state.player_scores).hash(&mut hasher);
+    for (transform, linear_velocity, ball_state) in ball_query {
+        let position = transform.translation.truncate();
+        (position.to_array().map(f32::to_bits), linear_velocity.to_array().map(f32::to_bits), ball_state.active).hash(&mut hasher);
+    }
+    // entities may come in a different order on the other side
+    let mut paddles: Vec<(usize, u32)> = rectangle_query
+        .iter()
+        .map(|(transform, state)| (state.player, transform.translation.x.to_bits()))
+        .collect();
+    paddles.sort();
+    paddles.hash(&mut hasher);
+    let mut bricks: Vec<([u32; 2], i32)> = brick_query
+        .iter()
+        .map(|(transform, state)| (transform.translation.truncate().to_array().map(f32::to_bits), state.hit_bar))
+        .collect();
+    bricks.sort();
+    bricks.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn local_input(keyboard_input: &ButtonInput<KeyCode>, controls: &ControlSettings) -> PaddleInput {
+    // either set of keys steers the local paddle
+    PaddleInput {
+        left: controls.players.iter().any(|keys| keyboard_input.pressed(keys.left)),
+        right: controls.players.iter().any(|keys| keyboard_input.pressed(keys.right)),
+        launch: keyboard_input.just_pressed(controls.launch),
+    }
+}
+
+// decides whether the game moves on this frame and with which inputs
+#[allow(clippy::too_many_arguments)]
+fn advance_frame(
+    mut network: ResMut<Network>,
+    mut player_inputs: ResMut<PlayerInputs>,
+    mut time_update_strategy: ResMut<TimeUpdateStrategy>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    controls: Res<ControlSettings>,
+    game_state: Res<GameState>,
+    ball_query: Query<(&Transform, &LinearVelocity, &BallState)>,
+    rectangle_query: Query<(&Transform, &PlayerRectangleState)>,
+    brick_query: Query<(&Transform, &BrickState)>,
+) {
+    let local_player = network.role.player();
+    let session = &mut network.session;
+    if !session.active {
+        return;
+    }
+    // the state after the frames played so far, the same on both sides however long each waited
+    let frame = session.frame;
+    if frame > 0 && frame.is_multiple_of(CHECKSUM_INTERVAL) && session.latest_checksum.map(|(latest, _)| latest) != Some(frame) {
+        let checksum = checksum(&game_state, &ball_query, &rectangle_query, &brick_query);
+        session.local_checksums.insert(frame, checksum);
+        session.latest_checksum = Some((frame, checksum));
+        compare_checksums(session);
+    }
+    match session.advance(local_input(&keyboard_input, &controls)) {
+        Some((local, remote)) => {
+            player_inputs.0[local_player] = local;
+            player_inputs.0[1 - local_player] = remote;
+        }
+        None => player_inputs.0 = default(),
+    }
+    // a frame that waits for the other side doesn't pass any time
+    let frame_time = if session.playing { Duration::from_secs_f64(FRAME_SECS) } else { Duration::ZERO };
+    *time_update_strategy = TimeUpdateStrategy::ManualDuration(frame_time);
+}
+
+fn send_messages(network: Res<Network>, field: Res<PlayingField>) {
+    let session = &network.session;
+    if network.refused.is_some() {
+        return;
+    }
+    // the host answers every hello, that keeps the connection alive until the first game
+    if !network.connected || session.game == 0 {
+        if network.role == Role::Guest {
+            network.send(&Message::Hello);
+        }
+        return;
+    }
+    if network.role == Role::Host && session.active && !session.peer_started {
+        network.send(&Message::Start {
+            game: session.game,
+            mode: session.mode,
+            seed: session.seed,
+            rules: session.rules,
+            field: *field,
+        });
+    }
+    // also after the game ended, the other side may still wait for the last frames
+    let skipped = session.local_inputs.len().saturating_sub(RESENT_INPUTS);
+    network.send(&Message::Inputs {
+        game: session.game,
+        first: session.local_first + skipped as u32,
+        inputs: session.local_inputs.iter().skip(skipped).copied().collect(),
+        checksum: session.latest_checksum,
+    });
+}
+
+fn begin_online_game(game_state: &mut GameState, physics_time: &mut Time<Physics>) {
+    game_state.online = true;
+    // exactly one physics step per frame of the game
+    physics_time.set_timestep_mode(TimestepMode::FixedOnce {
+        delta: Duration::from_secs_f64(FRAME_SECS),
+    });
+}
+
+fn end_online_game(
+    game_state: &mut GameState,
+    physics_time: &mut Time<Physics>,
+    time_update_strategy: &mut TimeUpdateStrategy,
+) {
+    game_state.online = false;
+    physics_time.set_timestep_mode(Time::<Physics>::default().timestep_mode());
+    physics_time.unpause();
+    *time_update_strategy = TimeUpdateStrategy::Automatic;
+}
+
+// the host starts a session with every co-op or versus game, both sides end it with the game
+#[allow(clippy::too_many_arguments)]
+fn start_and_end_sessions(
+    mut network: ResMut<Network>,
+    mut new_game_event: EventReader<NewGameEvent>,
+    coop_settings: Res<CoopSettings>,
+    difficulty: Res<DifficultySettings>,
+    mut game_over_event: EventReader<GameOverEvent>,
+    mut game_won_event: EventReader<GameWonEvent>,
+    mut start_demo_event: EventReader<StartDemoEvent>,
+    mut game_state: ResMut<GameState>,
+    mut physics_time: ResMut<Time<Physics>>,
+    mut time_update_strategy: ResMut<TimeUpdateStrategy>,
+) {
+    let ended = game_over_event.read().count() + game_won_event.read().count() + start_demo_event.read().count() > 0;
+    if ended && network.session.active {
+        network.session.active = false;
+        end_online_game(&mut game_state, &mut physics_time, &mut time_update_strategy);
+    }
+    let started = new_game_event
+        .read()
+        .filter(|event| matches!(event.mode, GameMode::Coop | GameMode::Versus))
+        .last();
+    if let Some(event) = started {
+        if network.role == Role::Host && network.connected {
+            let game = network.session.game + 1;
+            let rules = GameRules::for_new_game(Some(event), &coop_settings, &difficulty);
+            network.session = Session::new(game, event.mode, event.seed, rules, HOST_START_FRAMES);
+            begin_online_game(&mut game_state, &mut physics_time);
+        }
+    }
+}
+
+fn hold_physics(network: Res<Network>, mut physics_time: ResMut<Time<Physics>>) {
+    if !network.session.active {
+        return;
+    }
+    if network.session.playing {
+        physics_time.unpause();
+    } else {
+        physics_time.pause();
+    }
+}
+
+fn network_status(network: Res<Network>, mut text_query: Query<&mut Text, With<NetworkStatus>>) {
+    let session = &network.session;
+    let value = if let Some(reason) = &network.refused {
+        format!("Can't play online: {}", reason)
+    } else if let Some(frame) = session.desync {
+        format!("Out of sync since frame {}", frame)
+    } else if !network.connected {
+        match network.role {
+            Role::Host => format!("Waiting for a player on {}", network.socket.local_addr().map_or(String::new(), |address| address.to_string())),
+            Role::Guest => "Connecting...".to_string(),
+        }
+    } else if session.active && !session.playing && session.hold == 0 {
+        "Waiting for the other player".to_string()
+    } else if session.active {
+        format!("Online, player {}", network.role.player() + 1)
+    } else {
+        match network.role {
+            Role::Host => "Connected, start a co-op or versus game".to_string(),
+            Role::Guest => "Connected, waiting for the host to start a game".to_string(),
+        }
+    };
+    let mut text = text_query.single_mut();
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEFT: PaddleInput = PaddleInput { left: true, right: false, launch: false };
+    const RIGHT: PaddleInput = PaddleInput { left: false, right: true, launch: false };
+    const LAUNCH: PaddleInput = PaddleInput { left: false, right: false, launch: true };
+
+    fn session(hold: u32) -> Session {
+        Session::new(1, GameMode::Coop, None, GameRules::default(), hold)
+    }
+
+    #[test]
+    fn advance_holds_then_plays_delayed_inputs() {
+        let mut session = session(2);
+        for _ in 0..2 {
+            assert_eq!(session.advance(LEFT), None);
+            assert!(!session.playing);
+        }
+        // the first frames are played with empty inputs on both sides
+        for frame in 0..INPUT_DELAY {
+            assert_eq!(session.advance(LEFT), Some((PaddleInput::default(), PaddleInput::default())));
+            assert!(session.playing);
+            assert_eq!(session.frame, frame + 1);
+        }
+        // the input sampled after the hold comes INPUT_DELAY frames later, once the remote one is there
+        assert_eq!(session.advance(RIGHT), None);
+        assert!(!session.playing);
+        assert_eq!(session.frame, INPUT_DELAY);
+        session.add_remote_inputs(INPUT_DELAY, vec![RIGHT]);
+        assert_eq!(session.advance(RIGHT), Some((LEFT, RIGHT)));
+        assert_eq!(session.frame, INPUT_DELAY + 1);
+    }
+
+    #[test]
+    fn launch_while_waiting_is_kept() {
+        let mut session = session(0);
+        for _ in 0..INPUT_DELAY {
+            session.advance(PaddleInput::default());
+        }
+        // the input for this wait was sampled already, the launch goes with the next one
+        assert_eq!(session.advance(PaddleInput::default()), None);
+        assert_eq!(session.advance(LAUNCH), None);
+        session.add_remote_inputs(INPUT_DELAY, vec![PaddleInput::default(); 2]);
+        assert_eq!(session.advance(PaddleInput::default()).map(|(local, _)| local.launch), Some(false));
+        assert!(session.launch_pressed);
+        assert!(session.advance(PaddleInput::default()).is_some());
+        assert!(session.local_inputs.back().is_some_and(|input| input.launch));
+        assert!(!session.launch_pressed);
+    }
+
+    #[test]
+    fn remote_inputs_outside_the_window_are_rejected() {
+        let mut session = session(0);
+        session.add_remote_inputs(INPUT_WINDOW, vec![LEFT; 4]);
+        assert_eq!(session.remote_inputs.len(), INPUT_DELAY as usize);
+        session.add_remote_inputs(u32::MAX, vec![LEFT; 4]);
+        assert_eq!(session.remote_inputs.len(), INPUT_DELAY as usize);
+        session.add_remote_inputs(INPUT_WINDOW - 1, vec![LEFT; 2]);
+        assert_eq!(session.remote_inputs.len(), INPUT_WINDOW as usize);
+        assert_eq!(session.remote_inputs.back(), Some(&Some(LEFT)));
+        assert_eq!(session.remote_inputs[INPUT_DELAY as usize], None);
+    }
+
+    #[test]
+    fn advance_drops_old_inputs_and_checksums() {
+        let mut session = session(0);
+        session.local_checksums.insert(0, 1);
+        session.remote_checksums.insert(0, 2);
+        let frames = CHECKSUM_WINDOW + 1;
+        for frame in 0..frames {
+            session.add_remote_inputs(frame, vec![RIGHT]);
+            assert!(session.advance(LEFT).is_some());
+            assert!(session.local_inputs.len() <= RESENT_INPUTS);
+            assert_eq!(session.local_end(), session.frame + INPUT_DELAY);
+        }
+        assert_eq!(session.frame, frames);
+        assert!(session.remote_inputs.is_empty());
+        assert!(session.local_checksums.is_empty() && session.remote_checksums.is_empty());
+        // a checksum from long ago or from too far ahead isn't kept
+        session.add_remote_checksum(0, 3);
+        session.add_remote_checksum(u32::MAX, 3);
+        assert!(session.remote_checksums.is_empty());
+    }
+
+    #[test]
+    fn compare_checksums_finds_the_desync() {
+        let mut session = session(0);
+        session.local_checksums.insert(30, 7);
+        session.remote_checksums.insert(30, 7);
+        // a frame the local side hasn't reached yet waits
+        session.remote_checksums.insert(60, 8);
+        compare_checksums(&mut session);
+        assert_eq!(session.desync, None);
+        assert!(!session.local_checksums.contains_key(&30) && !session.remote_checksums.contains_key(&30));
+        assert!(session.remote_checksums.contains_key(&60));
+        session.local_checksums.insert(60, 9);
+        compare_checksums(&mut session);
+        assert_eq!(session.desync, Some(60));
+        // the first frame out of sync is the one reported
+        session.local_checksums.insert(90, 1);
+        session.remote_checksums.insert(90, 2);
+        compare_checksums(&mut session);
+        assert_eq!(session.desync, Some(60));
+    }
+
+    #[test]
+    fn check_field_refuses_a_different_field() {
+        let field = PlayingField::default();
+        assert!(check_field(&field, &field).is_ok());
+        let fullscreen = PlayingField { width: 1920.0, height: 1080.0 };
+        assert!(check_field(&fullscreen, &field).is_err());
+        assert!(check_field(&PlayingField { height: 721.0, ..field }, &field).is_err());
+    }
+
+    #[test]
+    fn messages_survive_ron() {
+        let messages = [
+            Message::Hello,
+            Message::Welcome,
+            Message::Start {
+                game: 2,
+                mode: GameMode::Versus,
+                seed: Some(42),
+                rules: GameRules { shared_lives: false, ball_speed: 950.5, lives: 4 },
+                field: PlayingField::default(),
+            },
+            Message::Inputs { game: 2, first: 17, inputs: vec![LEFT, RIGHT, LAUNCH], checksum: Some((30, u64::MAX)) },
+        ];
+        for message in &messages {
+            let contents = ron::to_string(message).unwrap();
+            assert!(contents.len() <= MAX_PACKET_SIZE);
+            let parsed: Message = ron::from_str(&contents).unwrap();
+            assert_eq!(ron::to_string(&parsed).unwrap(), contents);
+        }
+        match ron::from_str(&ron::to_string(&messages[3]).unwrap()) {
+            Ok(Message::Inputs { game, first, inputs, checksum }) => {
+                assert_eq!((game, first, inputs, checksum), (2, 17, vec![LEFT, RIGHT, LAUNCH], Some((30, u64::MAX))));
+            }
+            _ => panic!("inputs did not come back as inputs"),
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use bevy_xpbd_2d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+use std::time::Duration;
+
+use crate::create_objects::PlayingField;
+use crate::game_logic::{BrickDestroyedEvent, BrickHitEvent, WallHitEvent};
+use crate::theme::Theme;
+
+pub struct ParticlesPlugin;
+
+const PARTICLE_GRAVITY: f32 = -900.0;
+const PARTICLE_LIFETIME_SECS: f32 = 0.8;
+// sparks where the ball touches a brick or a wall spread this far to each side of its bounce
+const SPARK_SPREAD: f32 = 0.6;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ParticleLevel {
+    Off,
+    Reduced,
+    Full,
+}
+
+impl ParticleLevel {
+    pub fn burst_size(&self) -> usize {
+        match self {
+            ParticleLevel::Off => 0,
+            ParticleLevel::Reduced => 6,
+            ParticleLevel::Full => 16,
+        }
+    }
+
+    pub fn spark_count(&self) -> usize {
+        self.burst_size() / 4
+    }
+
+    pub fn next(&self) -> ParticleLevel {
+        match self {
+            ParticleLevel::Off => ParticleLevel::Reduced,
+            ParticleLevel::Reduced => ParticleLevel::Full,
+            ParticleLevel::Full => ParticleLevel::Off,
+        }
+    }
+}
+
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParticleSettings {
+    pub level: ParticleLevel,
+}
+
+impl Default for ParticleSettings {
+    fn default() -> Self {
+        ParticleSettings {
+            level: ParticleLevel::Full,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct ParticleMesh(Mesh2dHandle);
+
+#[derive(Component)]
+struct Particle {
+    velocity: Vec2,
+    lifetime: Timer,
+}
+
+impl Plugin for ParticlesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup)
+            .add_systems(Update, (spawn_brick_fragments, spawn_sparks, update_particles))
+            .init_resource::<ParticleSettings>();
+    }
+}
+
+fn setup(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>, field: Res<PlayingField>) {
+    let size = field.height / 120.0;
+    commands.insert_resource(ParticleMesh(Mesh2dHandle(meshes.add(Rectangle::new(size, size)))));
+}
+
+fn spawn_brick_fragments(
+    mut commands: Commands,
+    mut brick_destroyed_event: EventReader<BrickDestroyedEvent>,
+    particle_settings: Res<ParticleSettings>,
+    particle_mesh: Res<ParticleMesh>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for event in brick_destroyed_event.read() {
+        let count = particle_settings.level.burst_size();
+        if count == 0 {
+            continue;
+        }
+        // one material per burst, all of its fragments fade together
+        let material = materials.add(event.color);
+        for i in 0..count {
+            // spread evenly around the brick, alternating speeds so the burst does not look like a ring
+            let angle = i as f32 / count as f32 * TAU;
+            let speed = if i % 2 == 0 { 250.0 } else { 400.0 };
+            commands.spawn((
+                MaterialMesh2dBundle {
+                    mesh: particle_mesh.0.clone(),
+                    material: material.clone(),
+                    transform: Transform::from_translation(event.position.extend(1.0))
+                        .with_rotation(Quat::from_rotation_z(angle)),
+                    ..default()
+                },
+                Particle {
+                    velocity: Vec2::from_angle(angle) * speed,
+                    lifetime: Timer::new(Duration::from_secs_f32(PARTICLE_LIFETIME_SECS), TimerMode::Once),
+                },
+            ));
+        }
+    }
+}
+
+// a few sparks at the contact point, flying off with the ball
+#[allow(clippy::too_many_arguments)]
+fn spawn_sparks(
+    mut commands: Commands,
+    mut brick_hit_event: EventReader<BrickHitEvent>,
+    mut wall_hit_event: EventReader<WallHitEvent>,
+    ball_query: Query<&LinearVelocity>,
+    particle_settings: Res<ParticleSettings>,
+    particle_mesh: Res<ParticleMesh>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    theme: Res<Theme>,
+) {
+    let hits: Vec<(Entity, Vec2)> = brick_hit_event
+        .read()
+        .map(|event| (event.ball, event.position))
+        .chain(wall_hit_event.read().map(|event| (event.ball, event.position)))
+        .collect();
+    let count = particle_settings.level.spark_count();
+    if count == 0 {
+        return;
+    }
+    for (ball, position) in hits {
+        // the collision already turned the ball around
+        let Ok(velocity) = ball_query.get(ball) else {
+            continue;
+        };
+        let direction = velocity.0.normalize_or_zero();
+        if direction == Vec2::ZERO {
+            continue;
+        }
+        let material = materials.add(theme.ball);
+        for i in 0..count {
+            let turn = (i as f32 / (count - 1).max(1) as f32 * 2.0 - 1.0) * SPARK_SPREAD;
+            let spark_direction = Vec2::from_angle(turn).rotate(direction);
+            commands.spawn((
+                MaterialMesh2dBundle {
+                    mesh: particle_mesh.0.clone(),
+                    material: material.clone(),
+                    transform: Transform::from_translation(position.extend(1.0)).with_scale(Vec3::splat(0.5)),
+                    ..default()
+                },
+                Particle {
+                    velocity: spark_direction * 300.0,
+                    lifetime: Timer::new(Duration::from_secs_f32(PARTICLE_LIFETIME_SECS / 2.0), TimerMode::Once),
+                },
+            ));
+        }
+    }
+}
+
+fn update_particles(
+    mut commands: Commands,
+    mut particle_query: Query<(Entity, &mut Particle, &mut Transform, &Handle<ColorMaterial>)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    time: Res<Time>,
+) {
+    for (entity, mut particle, mut transform, material) in &mut particle_query {
+        particle.lifetime.tick(time.delta());
+        if particle.lifetime.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        particle.velocity.y += PARTICLE_GRAVITY * time.delta_seconds();
+        transform.translation += (particle.velocity * time.delta_seconds()).extend(0.0);
+        if let Some(material) = materials.get_mut(material) {
+            material.color.set_a(1.0 - particle.lifetime.fraction());
+        }
+    }
+}
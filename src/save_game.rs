@@ -0,0 +1,337 @@
+use bevy::app::AppExit;
+use bevy::{prelude::*, sprite::Mesh2dHandle};
+use bevy_xpbd_2d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::Duration;
+
+use crate::brick_damage::BrickMaterials;
+use crate::create_objects::{upright_uvs, BallState, BrickBundle, BrickState, BrickVisualBundle, PlayerRectangleState, PlayingField};
+use crate::endless::EndlessState;
+use crate::game_logic::{GameMode, GameState, HideContainersEvent};
+use crate::level::LevelGrid;
+use crate::theme::Theme;
+use crate::ui::ButtonAction;
+
+const SAVED_GAME_PATH: &str = "saved_game.ron";
+
+// a game left by closing the window is saved and can be continued from the start screen
+pub struct SaveGamePlugin;
+
+#[derive(Event)]
+pub struct ContinueGameEvent;
+
+#[derive(Serialize, Deserialize, PartialEq)]
+struct SavedBall {
+    x: f32,
+    y: f32,
+    velocity_x: f32,
+    velocity_y: f32,
+    active: bool,
+    // the game keeps the ball speed it was started with
+    speed: f32,
+    last_player: usize,
+    server: usize,
+    contact_secs: f32,
+}
+
+#[derive(Serialize, Deserialize, PartialEq)]
+struct SavedPaddle {
+    player: usize,
+    x: f32,
+}
+
+#[derive(Serialize, Deserialize, PartialEq)]
+struct SavedBrick {
+    x: f32,
+    y: f32,
+    hit_bar: i32,
+    max_hit_bar: i32,
+    row: i32,
+}
+
+#[derive(Serialize, Deserialize, PartialEq)]
+struct SavedGame {
+    mode: GameMode,
+    seed: Option<u32>,
+    level: u32,
+    score: u32,
+    player_scores: [u32; 2],
+    lives: i32,
+    player_lives: [i32; 2],
+    elapsed: f32,
+    time_left: f32,
+    combo: u32,
+    // the rules the game was started with, co-op lives may have been changed since
+    shared_lives: bool,
+    endless_rows: u32,
+    endless_row_secs: f32,
+    endless_row_elapsed: f32,
+    ball: SavedBall,
+    paddles: Vec<SavedPaddle>,
+    bricks: Vec<SavedBrick>,
+    // the bricks get the size they had on the field the game was played on
+    #[serde(default)]
+    field: PlayingField,
+}
+
+// the game on disk, None once it was continued
+#[derive(Resource, Default)]
+struct SavedGameSlot(Option<SavedGame>);
+
+// paddle positions of the continued game, placed once the paddles of its mode are spawned
+#[derive(Resource, Default)]
+struct PendingPaddles(Vec<SavedPaddle>);
+
+impl Plugin for SaveGamePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SavedGameSlot(load_saved_game()))
+            .init_resource::<PendingPaddles>()
+            .add_event::<ContinueGameEvent>()
+            // the game is restored before Update, so the co-op paddles are arranged for its mode
+            // in the same frame, and the paddles are placed after that
+            .add_systems(PreUpdate, continue_game)
+            .add_systems(PostUpdate, place_paddles.before(PhysicsSet::Prepare))
+            .add_systems(Update, continue_button)
+            .add_systems(Last, save_on_exit);
+    }
+}
+
+fn load_saved_game() -> Option<SavedGame> {
+    let contents = fs::read_to_string(SAVED_GAME_PATH).ok()?;
+    match ron::from_str(&contents) {
+        Ok(saved_game) => Some(saved_game),
+        Err(error) => {
+            warn!("invalid {}, nothing to continue: {}", SAVED_GAME_PATH, error);
+            None
+        }
+    }
+}
+
+// only a game played on this machine can be continued, not the demo, a test of the editor
+// level or a networked game
+#[allow(clippy::too_many_arguments)]
+fn save_on_exit(
+    mut app_exit_event: EventReader<AppExit>,
+    game_state: Res<GameState>,
+    endless_state: Res<EndlessState>,
+    field: Res<PlayingField>,
+    ball_query: Query<(&Transform, &LinearVelocity, &BallState)>,
+    rectangle_query: Query<(&Transform, &PlayerRectangleState)>,
+    brick_query: Query<(&Transform, &BrickState)>,
+) {
+    if app_exit_event.read().count() == 0 {
+        return;
+    }
+    if !game_state.in_game || game_state.demo || game_state.editing || game_state.online {
+        return;
+    }
+    let Ok((ball_transform, linear_velocity, ball_state)) = ball_query.get_single() else {
+        return;
+    };
+    let saved_game = SavedGame {
+        mode: game_state.mode,
+        seed: game_state.seed,
+        level: game_state.level,
+        score: game_state.score,
+        player_scores: game_state.player_scores,
+        lives: game_state.lives,
+        player_lives: game_state.player_lives,
+        elapsed: game_state.elapsed,
+        time_left: game_state.time_left,
+        combo: game_state.combo,
+        shared_lives: game_state.rules.shared_lives,
+        endless_rows: endless_state.rows,
+        endless_row_secs: endless_state.row_timer.duration().as_secs_f32(),
+        endless_row_elapsed: endless_state.row_timer.elapsed_secs(),
+        ball: SavedBall {
+            x: ball_transform.translation.x,
+            y: ball_transform.translation.y,
+            velocity_x: linear_velocity.x,
+            velocity_y: linear_velocity.y,
+            active: ball_state.active,
+            speed: ball_state.speed,
+            last_player: ball_state.last_player,
+            server: ball_state.server,
+            contact_secs: ball_state.contact_timer.elapsed_secs(),
+        },
+        paddles: rectangle_query
+            .iter()
+            .map(|(transform, state)| SavedPaddle {
+                player: state.player,
+                x: transform.translation.x,
+            })
+            .collect(),
+        bricks: brick_query
+            .iter()
+            .map(|(transform, state)| SavedBrick {
+                x: transform.translation.x,
+                y: transform.translation.y,
+                hit_bar: state.hit_bar,
+                max_hit_bar: state.max_hit_bar,
+                row: state.row,
+            })
+            .collect(),
+        field: *field,
+    };
+    match ron::ser::to_string_pretty(&saved_game, ron::ser::PrettyConfig::default()) {
+        Ok(contents) => {
+            if let Err(error) = fs::write(SAVED_GAME_PATH, contents) {
+                warn!("could not save {}: {}", SAVED_GAME_PATH, error);
+            }
+        }
+        Err(error) => warn!("could not serialize the game: {}", error),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn continue_game(
+    mut commands: Commands,
+    mut continue_game_event: EventReader<ContinueGameEvent>,
+    mut slot: ResMut<SavedGameSlot>,
+    mut pending_paddles: ResMut<PendingPaddles>,
+    mut game_state: ResMut<GameState>,
+    mut endless_state: ResMut<EndlessState>,
+    mut ball_query: Query<(&mut Transform, &mut LinearVelocity, &mut BallState)>,
+    brick_query: Query<Entity, With<BrickState>>,
+    mut physics_time: ResMut<Time<Physics>>,
+    mut hide_containers_event: EventWriter<HideContainersEvent>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut brick_materials: ResMut<BrickMaterials>,
+    theme: Res<Theme>,
+) {
+    if continue_game_event.read().count() == 0 {
+        return;
+    }
+    let Ok((mut transform, mut linear_velocity, mut ball_state)) = ball_query.get_single_mut() else {
+        return;
+    };
+    let Some(saved_game) = slot.0.take() else {
+        return;
+    };
+    // a game can be continued once, like it could only be played on once
+    if let Err(error) = fs::remove_file(SAVED_GAME_PATH) {
+        warn!("could not remove {}: {}", SAVED_GAME_PATH, error);
+    }
+    game_state.in_game = true;
+    game_state.demo = false;
+    game_state.online = false;
+    game_state.editing = false;
+    game_state.mode = saved_game.mode;
+    game_state.seed = saved_game.seed;
+    game_state.level = saved_game.level;
+    game_state.score = saved_game.score;
+    game_state.player_scores = saved_game.player_scores;
+    game_state.lives = saved_game.lives;
+    game_state.player_lives = saved_game.player_lives;
+    game_state.elapsed = saved_game.elapsed;
+    game_state.time_left = saved_game.time_left;
+    game_state.combo = saved_game.combo;
+    game_state.rules.shared_lives = saved_game.shared_lives;
+    game_state.set_bricks(saved_game.bricks.len() as i32);
+    // the game comes back paused, so the player has a moment before the ball moves again
+    game_state.paused = true;
+    physics_time.pause();
+    endless_state.rows = saved_game.endless_rows;
+    endless_state.row_timer.set_duration(Duration::from_secs_f32(saved_game.endless_row_secs));
+    endless_state.row_timer.set_elapsed(Duration::from_secs_f32(saved_game.endless_row_elapsed));
+    let ball = &saved_game.ball;
+    transform.translation.x = ball.x;
+    transform.translation.y = ball.y;
+    linear_velocity.0 = Vec2::new(ball.velocity_x, ball.velocity_y);
+    ball_state.active = ball.active;
+    ball_state.speed = ball.speed;
+    ball_state.last_player = ball.last_player;
+    ball_state.server = ball.server;
+    ball_state.contact_timer.set_elapsed(Duration::from_secs_f32(ball.contact_secs));
+    for entity in &brick_query {
+        commands.entity(entity).despawn_recursive();
+    }
+    let grid = LevelGrid::new(&saved_game.field);
+    let mesh = Mesh2dHandle(meshes.add(upright_uvs(Rectangle::new(grid.brick_height, grid.brick_width).into())));
+    for brick in &saved_game.bricks {
+        let state = BrickState {
+            width: grid.brick_width,
+            height: grid.brick_height,
+            hit_bar: brick.hit_bar,
+            max_hit_bar: brick.max_hit_bar,
+            row: brick.row,
+        };
+        let material = brick_materials.get(&state, &theme, &mut materials);
+        commands.spawn(BrickBundle::new(state, Vec2::new(brick.x, brick.y))).with_children(|parent| {
+            parent.spawn(BrickVisualBundle::new(mesh.clone(), material));
+        });
+    }
+    pending_paddles.0 = saved_game.paddles;
+    hide_containers_event.send(HideContainersEvent);
+}
+
+fn place_paddles(
+    mut pending_paddles: ResMut<PendingPaddles>,
+    mut rectangle_query: Query<(&mut Transform, &PlayerRectangleState)>,
+) {
+    if pending_paddles.0.is_empty() {
+        return;
+    }
+    for (mut transform, state) in &mut rectangle_query {
+        if let Some(paddle) = pending_paddles.0.iter().find(|paddle| paddle.player == state.player) {
+            transform.translation.x = paddle.x.clamp(state.min_x, state.max_x);
+        }
+    }
+    pending_paddles.0.clear();
+}
+
+// the continue button only shows while there is a game to continue
+fn continue_button(slot: Res<SavedGameSlot>, mut button_query: Query<(&ButtonAction, &mut Style)>) {
+    if !slot.is_changed() {
+        return;
+    }
+    for (action, mut style) in &mut button_query {
+        if matches!(action, ButtonAction::Continue) {
+            style.display = if slot.0.is_some() { Display::Flex } else { Display::None };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_game_survives_ron() {
+        let saved_game = SavedGame {
+            mode: GameMode::Coop,
+            seed: Some(7),
+            level: 3,
+            score: 1250,
+            player_scores: [1000, 250],
+            lives: 4,
+            player_lives: [3, 1],
+            elapsed: 61.25,
+            time_left: 0.0,
+            combo: 2,
+            shared_lives: false,
+            endless_rows: 6,
+            endless_row_secs: 11.7,
+            endless_row_elapsed: 0.1,
+            ball: SavedBall {
+                x: -12.5,
+                y: 40.0,
+                velocity_x: 310.0,
+                velocity_y: -742.0,
+                active: true,
+                speed: 800.0,
+                last_player: 1,
+                server: 0,
+                contact_secs: 1.5,
+            },
+            paddles: vec![SavedPaddle { player: 0, x: 200.0 }, SavedPaddle { player: 1, x: -180.0 }],
+            bricks: vec![SavedBrick { x: 0.0, y: 180.0, hit_bar: 2, max_hit_bar: 3, row: 4 }],
+            field: PlayingField { width: 1920.0, height: 1080.0 },
+        };
+        let contents = ron::ser::to_string_pretty(&saved_game, ron::ser::PrettyConfig::default()).unwrap();
+        let loaded: SavedGame = ron::from_str(&contents).unwrap();
+        assert!(loaded == saved_game);
+    }
+}
@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::game_logic::{BallLostEvent, BrickDestroyedEvent, GameState};
+
+pub struct ScreenEffectsPlugin;
+
+// largest camera offset in pixels at full trauma
+const MAX_SHAKE_OFFSET: f32 = 18.0;
+const MAX_SHAKE_ANGLE: f32 = 0.03;
+// trauma lost per second
+const SHAKE_DECAY: f32 = 1.5;
+const HIT_STOP_SECS: f32 = 0.12;
+const HIT_STOP_SPEED: f32 = 0.05;
+// events at least this intense also freeze the game briefly
+const HIT_STOP_INTENSITY: f32 = 1.0;
+
+const BRICK_DESTROYED_INTENSITY: f32 = 0.3;
+const BALL_LOST_INTENSITY: f32 = 1.0;
+
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScreenEffectsSettings {
+    // accessibility: disables both camera shake and hit-stop
+    pub enabled: bool,
+}
+
+impl Default for ScreenEffectsSettings {
+    fn default() -> Self {
+        ScreenEffectsSettings { enabled: true }
+    }
+}
+
+#[derive(Component, Default)]
+pub struct CameraShake {
+    // between 0 and 1, the shake grows with its square
+    pub trauma: f32,
+}
+
+#[derive(Resource, Default)]
+struct HitStop {
+    remaining: f32,
+}
+
+impl Plugin for ScreenEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (add_trauma, shake_camera, update_hit_stop).chain())
+            .init_resource::<ScreenEffectsSettings>()
+            .init_resource::<HitStop>();
+    }
+}
+
+fn add_trauma(
+    mut brick_destroyed_event: EventReader<BrickDestroyedEvent>,
+    mut ball_lost_event: EventReader<BallLostEvent>,
+    settings: Res<ScreenEffectsSettings>,
+    mut camera_query: Query<&mut CameraShake>,
+    mut hit_stop: ResMut<HitStop>,
+    game_state: Res<GameState>,
+) {
+    let intensity = brick_destroyed_event.read().count() as f32 * BRICK_DESTROYED_INTENSITY
+        + ball_lost_event.read().count() as f32 * BALL_LOST_INTENSITY;
+    // the demo behind the start screen plays without the feedback
+    if intensity <= 0.0 || !settings.enabled || game_state.demo {
+        return;
+    }
+    for mut camera_shake in &mut camera_query {
+        camera_shake.trauma = (camera_shake.trauma + intensity).min(1.0);
+    }
+    // slowing down one side of a networked game would throw it out of step
+    if intensity >= HIT_STOP_INTENSITY && !game_state.online {
+        hit_stop.remaining = HIT_STOP_SECS;
+    }
+}
+
+fn shake_camera(
+    mut camera_query: Query<(&mut CameraShake, &mut Transform)>,
+    settings: Res<ScreenEffectsSettings>,
+    // real time keeps the shake going during hit-stop
+    time: Res<Time<Real>>,
+) {
+    for (mut camera_shake, mut transform) in &mut camera_query {
+        if !settings.enabled {
+            camera_shake.trauma = 0.0;
+        }
+        camera_shake.trauma = (camera_shake.trauma - SHAKE_DECAY * time.delta_seconds()).max(0.0);
+        let shake = camera_shake.trauma * camera_shake.trauma;
+        let t = time.elapsed_seconds();
+        // a few unrelated sines are cheap and noisy enough for a shake
+        let x = (t * 47.0).sin() * (t * 13.0).cos();
+        let y = (t * 53.0).cos() * (t * 17.0).sin();
+        let angle = (t * 31.0).sin();
+        transform.translation.x = x * MAX_SHAKE_OFFSET * shake;
+        transform.translation.y = y * MAX_SHAKE_OFFSET * shake;
+        transform.rotation = Quat::from_rotation_z(angle * MAX_SHAKE_ANGLE * shake);
+    }
+}
+
+// the physics clock advances by real time, so it is slowed down along with the virtual one
+fn update_hit_stop(
+    mut hit_stop: ResMut<HitStop>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut physics_time: ResMut<Time<Physics>>,
+    real_time: Res<Time<Real>>,
+) {
+    if hit_stop.remaining > 0.0 {
+        hit_stop.remaining -= real_time.delta_seconds();
+        virtual_time.set_relative_speed(HIT_STOP_SPEED);
+        physics_time.set_relative_speed(HIT_STOP_SPEED);
+    } else if virtual_time.relative_speed() != 1.0 || physics_time.relative_speed() != 1.0 {
+        virtual_time.set_relative_speed(1.0);
+        physics_time.set_relative_speed(1.0);
+    }
+}
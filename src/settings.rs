@@ -0,0 +1,248 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::brick_health::BrickHealthSettings;
+use crate::coop::CoopSettings;
+use crate::display::{DisplaySettings, TEXT_SCALE_RANGE};
+use crate::game_logic::{ControlSettings, DifficultySettings, BALL_SPEED_RANGE, LIVES_RANGE};
+use crate::particles::ParticleSettings;
+use crate::screen_effects::ScreenEffectsSettings;
+use crate::sound::AudioSettings;
+use crate::theme::ThemeSettings;
+use crate::trail::{TrailSettings, MAX_TRAIL_LENGTH};
+
+const SETTINGS_PATH: &str = "settings.ron";
+
+pub struct SettingsPlugin;
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct SettingsFile {
+    audio: AudioSettings,
+    particles: ParticleSettings,
+    screen_effects: ScreenEffectsSettings,
+    trail: TrailSettings,
+    theme: ThemeSettings,
+    brick_health: BrickHealthSettings,
+    coop: CoopSettings,
+    difficulty: DifficultySettings,
+    controls: ControlSettings,
+    display: DisplaySettings,
+}
+
+// what was wrong with the settings file at startup, shown on the settings screen
+#[derive(Resource, Default)]
+pub struct SettingsProblems(pub Vec<String>);
+
+// every persisted settings resource, for systems that show or edit them
+#[derive(SystemParam)]
+pub struct SettingsResources<'w> {
+    pub audio: ResMut<'w, AudioSettings>,
+    pub particles: ResMut<'w, ParticleSettings>,
+    pub screen_effects: ResMut<'w, ScreenEffectsSettings>,
+    pub trail: ResMut<'w, TrailSettings>,
+    pub theme: ResMut<'w, ThemeSettings>,
+    pub brick_health: ResMut<'w, BrickHealthSettings>,
+    pub coop: ResMut<'w, CoopSettings>,
+    pub difficulty: ResMut<'w, DifficultySettings>,
+    pub controls: ResMut<'w, ControlSettings>,
+    pub display: ResMut<'w, DisplaySettings>,
+}
+
+impl<'w> SettingsResources<'w> {
+    pub fn is_changed(&self) -> bool {
+        self.audio.is_changed()
+            || self.particles.is_changed()
+            || self.screen_effects.is_changed()
+            || self.trail.is_changed()
+            || self.theme.is_changed()
+            || self.brick_health.is_changed()
+            || self.coop.is_changed()
+            || self.difficulty.is_changed()
+            || self.controls.is_changed()
+            || self.display.is_changed()
+    }
+
+    // is_changed is also true for the frame the resources were inserted
+    fn is_added(&self) -> bool {
+        self.audio.is_added()
+            && self.particles.is_added()
+            && self.screen_effects.is_added()
+            && self.trail.is_added()
+            && self.theme.is_added()
+            && self.brick_health.is_added()
+            && self.coop.is_added()
+            && self.difficulty.is_added()
+            && self.controls.is_added()
+            && self.display.is_added()
+    }
+
+    fn to_file(&self) -> SettingsFile {
+        SettingsFile {
+            audio: self.audio.clone(),
+            particles: self.particles.clone(),
+            screen_effects: self.screen_effects.clone(),
+            trail: self.trail.clone(),
+            theme: self.theme.clone(),
+            brick_health: self.brick_health.clone(),
+            coop: self.coop.clone(),
+            difficulty: self.difficulty.clone(),
+            controls: self.controls.clone(),
+            display: self.display.clone(),
+        }
+    }
+}
+
+impl SettingsFile {
+    // puts the default back in place of every value out of its range, with a note of what was wrong
+    fn validate(&mut self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let defaults = SettingsFile::default();
+        let mut check_range = |name: &str, value: &mut f32, (min, max): (f32, f32), default: f32| {
+            if !(min..=max).contains(value) {
+                problems.push(format!("{} {} is not between {} and {}, using {}", name, value, min, max, default));
+                *value = default;
+            }
+        };
+        check_range("audio.master_volume", &mut self.audio.master_volume, (0.0, 1.0), defaults.audio.master_volume);
+        check_range("audio.sfx_volume", &mut self.audio.sfx_volume, (0.0, 1.0), defaults.audio.sfx_volume);
+        check_range("audio.music_volume", &mut self.audio.music_volume, (0.0, 1.0), defaults.audio.music_volume);
+        check_range("difficulty.ball_speed", &mut self.difficulty.ball_speed, BALL_SPEED_RANGE, defaults.difficulty.ball_speed);
+        check_range("display.text_scale", &mut self.display.text_scale, TEXT_SCALE_RANGE, defaults.display.text_scale);
+        if self.trail.length > MAX_TRAIL_LENGTH {
+            problems.push(format!(
+                "trail.length {} is more than {}, using {}",
+                self.trail.length, MAX_TRAIL_LENGTH, defaults.trail.length
+            ));
+            self.trail.length = defaults.trail.length;
+        }
+        let (min_lives, max_lives) = LIVES_RANGE;
+        if !(min_lives..=max_lives).contains(&self.difficulty.lives) {
+            problems.push(format!(
+                "difficulty.lives {} is not between {} and {}, using {}",
+                self.difficulty.lives, min_lives, max_lives, defaults.difficulty.lives
+            ));
+            self.difficulty.lives = defaults.difficulty.lives;
+        }
+        // a key doing two things would do both at once
+        let keys: Vec<KeyCode> = ControlSettings::CONTROLS.iter().map(|control| self.controls.key(*control)).collect();
+        let key_problem = keys
+            .iter()
+            .enumerate()
+            .find_map(|(index, key)| keys[..index].contains(key).then(|| format!("controls use {:?} more than once", key)))
+            .or_else(|| {
+                keys.iter()
+                    .find(|key| ControlSettings::RESERVED_KEYS.contains(key))
+                    .map(|key| format!("controls use {:?}, which is reserved", key))
+            });
+        if let Some(problem) = key_problem {
+            problems.push(format!("{}, using the default keys", problem));
+            self.controls = defaults.controls;
+        }
+        problems
+    }
+}
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let (settings, problems) = load_settings();
+        app.insert_resource(settings.audio)
+            .insert_resource(settings.particles)
+            .insert_resource(settings.screen_effects)
+            .insert_resource(settings.trail)
+            .insert_resource(settings.theme)
+            .insert_resource(settings.brick_health)
+            .insert_resource(settings.coop)
+            .insert_resource(settings.difficulty)
+            .insert_resource(settings.controls)
+            .insert_resource(settings.display)
+            .insert_resource(problems)
+            .add_systems(Update, save_settings);
+    }
+}
+
+fn load_settings() -> (SettingsFile, SettingsProblems) {
+    let mut problems = Vec::new();
+    let mut settings = match fs::read_to_string(SETTINGS_PATH) {
+        Ok(contents) => ron::from_str(&contents).unwrap_or_else(|error| {
+            problems.push(format!("{}, using defaults", error));
+            SettingsFile::default()
+        }),
+        // no file yet, it is written on the first change
+        Err(_) => SettingsFile::default(),
+    };
+    problems.extend(settings.validate());
+    for problem in &problems {
+        warn!("invalid {}: {}", SETTINGS_PATH, problem);
+    }
+    (settings, SettingsProblems(problems))
+}
+
+fn save_settings(settings: SettingsResources) {
+    if settings.is_changed() && !settings.is_added() {
+        let settings = settings.to_file();
+        match ron::ser::to_string_pretty(&settings, ron::ser::PrettyConfig::default()) {
+            Ok(contents) => {
+                if let Err(error) = fs::write(SETTINGS_PATH, contents) {
+                    warn!("could not write {}: {}", SETTINGS_PATH, error);
+                }
+            }
+            Err(error) => warn!("could not serialize settings: {}", error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(change: impl FnOnce(&mut SettingsFile)) -> (SettingsFile, Vec<String>) {
+        let mut settings = SettingsFile::default();
+        change(&mut settings);
+        let problems = settings.validate();
+        (settings, problems)
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        assert!(problems(|_| {}).1.is_empty());
+    }
+
+    #[test]
+    fn out_of_range_values_fall_back_to_defaults() {
+        let defaults = SettingsFile::default();
+        let (settings, found) = problems(|settings| settings.audio.music_volume = 1.5);
+        assert_eq!(found.len(), 1);
+        assert_eq!(settings.audio.music_volume, defaults.audio.music_volume);
+        let (settings, found) = problems(|settings| settings.difficulty.ball_speed = f32::NAN);
+        assert_eq!(found.len(), 1);
+        assert_eq!(settings.difficulty.ball_speed, defaults.difficulty.ball_speed);
+        let (settings, found) = problems(|settings| settings.difficulty.lives = LIVES_RANGE.1 + 1);
+        assert_eq!(found.len(), 1);
+        assert_eq!(settings.difficulty.lives, defaults.difficulty.lives);
+        let (settings, found) = problems(|settings| settings.display.text_scale = TEXT_SCALE_RANGE.0 / 2.0);
+        assert_eq!(found.len(), 1);
+        assert_eq!(settings.display.text_scale, defaults.display.text_scale);
+        let (settings, found) = problems(|settings| settings.trail.length = MAX_TRAIL_LENGTH + 1);
+        assert_eq!(found.len(), 1);
+        assert_eq!(settings.trail.length, defaults.trail.length);
+        // the ends of a range are fine
+        let (_, found) = problems(|settings| settings.difficulty.ball_speed = BALL_SPEED_RANGE.1);
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn shared_or_reserved_keys_fall_back_to_default_controls() {
+        let defaults = ControlSettings::default();
+        let (settings, found) = problems(|settings| settings.controls.launch = settings.controls.players[1].left);
+        assert_eq!(found.len(), 1);
+        assert_eq!(settings.controls.launch, defaults.launch);
+        for key in ControlSettings::RESERVED_KEYS {
+            let (settings, found) = problems(|settings| settings.controls.players[0].right = key);
+            assert_eq!(found.len(), 1);
+            assert_eq!(settings.controls.players[0].right, defaults.players[0].right);
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use bevy::audio::{Pitch, PitchBundle, Volume};
+use std::time::Duration;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::create_objects::BrickState;
+use crate::game_logic::{
+    BallLostEvent, BrickDestroyedEvent, BrickHitEvent, GameOverEvent, GameState, GameWonEvent,
+    PaddleHitEvent, WallHitEvent,
+};
+
+pub struct SoundPlugin;
+
+// C4, the tone of a first hit on the lowest row
+const BRICK_BASE_FREQUENCY: f32 = 261.63;
+// semitone offsets of one octave of the major scale
+const MAJOR_SCALE: [i32; 7] = [0, 2, 4, 5, 7, 9, 11];
+// the climb stops four octaves up, longer combos and higher rows repeat the top tone
+const MAX_BRICK_DEGREE: i32 = 28;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BrickTone {
+    pub frequency: f32,
+    pub duration: f32,
+}
+
+// consecutive hits climb the major scale, higher rows start higher and
+// bricks that survive the hit sound an octave lower and shorter
+pub fn brick_hit_tone(combo: u32, row: i32, remaining_hits: i32) -> BrickTone {
+    let degree = (row.clamp(0, MAX_BRICK_DEGREE) + combo.saturating_sub(1).min(MAX_BRICK_DEGREE as u32) as i32).min(MAX_BRICK_DEGREE);
+    let semitones = 12 * (degree / 7) + MAJOR_SCALE[(degree % 7) as usize];
+    let mut frequency = BRICK_BASE_FREQUENCY * 2f32.powf(semitones as f32 / 12.0);
+    let mut duration = 0.15;
+    if remaining_hits > 0 {
+        frequency /= 2.0;
+        duration = 0.08;
+    }
+    BrickTone { frequency, duration }
+}
+
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master_volume: f32,
+    pub sfx_volume: f32,
+    pub music_volume: f32,
+    pub muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            master_volume: 1.0,
+            sfx_volume: 0.8,
+            music_volume: 0.5,
+            muted: false,
+        }
+    }
+}
+
+impl AudioSettings {
+    pub fn sfx(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.master_volume * self.sfx_volume
+        }
+    }
+
+    pub fn music(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.master_volume * self.music_volume
+        }
+    }
+}
+
+#[derive(Resource)]
+struct SoundEffects {
+    paddle_hit: Handle<AudioSource>,
+    wall_hit: Handle<AudioSource>,
+    brick_destroy: Handle<AudioSource>,
+    life_lost: Handle<AudioSource>,
+    game_won: Handle<AudioSource>,
+    game_over: Handle<AudioSource>,
+}
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup)
+            .add_systems(Update, toggle_mute)
+            // after the game logic so hits and destructions of one frame are seen together
+            .add_systems(PostUpdate, play_sound_effects)
+            .init_resource::<AudioSettings>();
+    }
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SoundEffects {
+        paddle_hit: asset_server.load("embedded://sounds/paddle_hit.wav"),
+        wall_hit: asset_server.load("embedded://sounds/wall_hit.wav"),
+        brick_destroy: asset_server.load("embedded://sounds/brick_destroy.wav"),
+        life_lost: asset_server.load("embedded://sounds/life_lost.wav"),
+        game_won: asset_server.load("embedded://sounds/game_won.wav"),
+        game_over: asset_server.load("embedded://sounds/game_over.wav"),
+    });
+}
+
+fn toggle_mute(keyboard_input: Res<ButtonInput<KeyCode>>, mut audio_settings: ResMut<AudioSettings>) {
+    if keyboard_input.just_pressed(KeyCode::KeyM) {
+        audio_settings.muted = !audio_settings.muted;
+    }
+}
+
+fn play(commands: &mut Commands, source: &Handle<AudioSource>, volume: f32) {
+    commands.spawn(AudioBundle {
+        source: source.clone(),
+        settings: PlaybackSettings::DESPAWN.with_volume(Volume::new(volume)),
+    });
+}
+
+fn play_tone(commands: &mut Commands, pitch_assets: &mut Assets<Pitch>, tone: BrickTone, volume: f32) {
+    commands.spawn(PitchBundle {
+        source: pitch_assets.add(Pitch::new(tone.frequency, Duration::from_secs_f32(tone.duration))),
+        settings: PlaybackSettings::DESPAWN.with_volume(Volume::new(volume)),
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn play_sound_effects(
+    mut commands: Commands,
+    mut pitch_assets: ResMut<Assets<Pitch>>,
+    brick_query: Query<&BrickState>,
+    sound_effects: Res<SoundEffects>,
+    audio_settings: Res<AudioSettings>,
+    game_state: Res<GameState>,
+    mut paddle_hit_event: EventReader<PaddleHitEvent>,
+    mut wall_hit_event: EventReader<WallHitEvent>,
+    mut brick_hit_event: EventReader<BrickHitEvent>,
+    mut brick_destroyed_event: EventReader<BrickDestroyedEvent>,
+    mut ball_lost_event: EventReader<BallLostEvent>,
+    mut game_won_event: EventReader<GameWonEvent>,
+    mut game_over_event: EventReader<GameOverEvent>,
+) {
+    let destroyed: Vec<(Entity, i32)> = brick_destroyed_event
+        .read()
+        .map(|event| (event.entity, event.row))
+        .collect();
+    let brick_hits: Vec<Entity> = brick_hit_event.read().map(|event| event.entity).collect();
+    let paddle_hits = paddle_hit_event.read().count();
+    let wall_hits = wall_hit_event.read().count();
+    let balls_lost = ball_lost_event.read().count();
+    let won = game_won_event.read().count() > 0;
+    let over = game_over_event.read().count() > 0;
+
+    let volume = audio_settings.sfx();
+    // the attract mode demo plays silently behind the start screen
+    if volume <= 0.0 || game_state.demo {
+        return;
+    }
+    if paddle_hits > 0 {
+        play(&mut commands, &sound_effects.paddle_hit, volume);
+    }
+    if wall_hits > 0 {
+        play(&mut commands, &sound_effects.wall_hit, volume);
+    }
+    if !destroyed.is_empty() {
+        play(&mut commands, &sound_effects.brick_destroy, volume);
+    }
+    // one tone per frame, pitched by the combo after this frame's hits
+    let last_hit = brick_hits.last().map(|entity| {
+        match destroyed.iter().find(|(destroyed_entity, _)| destroyed_entity == entity) {
+            Some((_, row)) => (*row, 0),
+            None => brick_query
+                .get(*entity)
+                .map(|brick_state| (brick_state.row, brick_state.hit_bar))
+                .unwrap_or((0, 0)),
+        }
+    });
+    if let Some((row, remaining_hits)) = last_hit {
+        let tone = brick_hit_tone(game_state.combo, row, remaining_hits);
+        play_tone(&mut commands, &mut pitch_assets, tone, volume);
+    }
+    if balls_lost > 0 {
+        play(&mut commands, &sound_effects.life_lost, volume);
+    }
+    if won {
+        play(&mut commands, &sound_effects.game_won, volume);
+    }
+    if over {
+        play(&mut commands, &sound_effects.game_over, volume);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tone_rises_with_combo() {
+        let tones: Vec<f32> = (1..=10).map(|combo| brick_hit_tone(combo, 0, 0).frequency).collect();
+        assert!(tones.windows(2).all(|pair| pair[1] > pair[0]));
+        assert_eq!(tones[0], BRICK_BASE_FREQUENCY);
+    }
+
+    #[test]
+    fn tone_varies_by_row_and_hit_bar() {
+        assert!(brick_hit_tone(1, 3, 0).frequency > brick_hit_tone(1, 0, 0).frequency);
+        let destroyed = brick_hit_tone(2, 1, 0);
+        let damaged = brick_hit_tone(2, 1, 2);
+        assert_eq!(damaged.frequency, destroyed.frequency / 2.0);
+        assert!(damaged.duration < destroyed.duration);
+    }
+
+    #[test]
+    fn tone_stays_in_range() {
+        let lowest = BRICK_BASE_FREQUENCY / 2.0;
+        let highest = brick_hit_tone(1, MAX_BRICK_DEGREE, 0).frequency;
+        for (combo, row) in [(0, -5), (1, 0), (50, 20), (u32::MAX, i32::MAX)] {
+            for remaining_hits in [0, 3] {
+                let frequency = brick_hit_tone(combo, row, remaining_hits).frequency;
+                assert!((lowest..=highest).contains(&frequency), "{} out of range", frequency);
+            }
+        }
+        assert_eq!(brick_hit_tone(u32::MAX, 0, 0), brick_hit_tone(100, 0, 0));
+    }
+}
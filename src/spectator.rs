@@ -0,0 +1,240 @@
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+use serde::Serialize;
+use std::env;
+use std::io::{ErrorKind, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+
+use crate::create_objects::{BallState, BrickState, PlayerRectangleState};
+use crate::game_logic::{GameMode, GameState};
+
+// publishes the game state for overlays and dashboards in other processes:
+// BRICK_BREAKER_SPECTATE=127.0.0.1:7100 accepts TCP connections on that address and
+// sends every client one JSON object per frame, each on its own line
+pub struct SpectatorPlugin;
+
+const SPECTATE_VAR: &str = "BRICK_BREAKER_SPECTATE";
+
+// a client that falls this far behind is dropped instead of buffering without end
+const MAX_PENDING_BYTES: usize = 1 << 20;
+
+#[derive(Serialize)]
+struct Snapshot {
+    tick: u64,
+    mode: GameMode,
+    in_game: bool,
+    demo: bool,
+    paused: bool,
+    online: bool,
+    level: u32,
+    score: u32,
+    lives: i32,
+    player_scores: [u32; 2],
+    player_lives: [i32; 2],
+    elapsed: f32,
+    time_left: f32,
+    balls: Vec<BallSnapshot>,
+    paddles: Vec<PaddleSnapshot>,
+    bricks: Vec<BrickSnapshot>,
+}
+
+#[derive(Serialize)]
+struct BallSnapshot {
+    x: f32,
+    y: f32,
+    velocity_x: f32,
+    velocity_y: f32,
+    radius: f32,
+    active: bool,
+}
+
+#[derive(Serialize)]
+struct PaddleSnapshot {
+    player: usize,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+#[derive(Serialize)]
+struct BrickSnapshot {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    hit_bar: i32,
+    max_hit_bar: i32,
+}
+
+struct Spectator {
+    stream: TcpStream,
+    address: SocketAddr,
+    // bytes the socket didn't take yet, written out first on the next frame
+    pending: Vec<u8>,
+}
+
+#[derive(Resource)]
+struct Broadcast {
+    listener: TcpListener,
+    spectators: Vec<Spectator>,
+    tick: u64,
+}
+
+impl Plugin for SpectatorPlugin {
+    fn build(&self, app: &mut App) {
+        let Some(broadcast) = open_broadcast() else {
+            return;
+        };
+        // at the end of the frame, once the physics step moved everything
+        app.insert_resource(broadcast)
+            .add_systems(Last, (accept_spectators, broadcast_state).chain());
+    }
+}
+
+fn open_broadcast() -> Option<Broadcast> {
+    let address = env::var(SPECTATE_VAR).ok()?;
+    let address: SocketAddr = match address.trim().parse() {
+        Ok(address) => address,
+        Err(error) => {
+            warn!("invalid spectator address {}, not broadcasting: {}", address, error);
+            return None;
+        }
+    };
+    let listener = match TcpListener::bind(address).and_then(|listener| listener.set_nonblocking(true).map(|_| listener)) {
+        Ok(listener) => listener,
+        Err(error) => {
+            warn!("could not listen on {}, not broadcasting: {}", address, error);
+            return None;
+        }
+    };
+    info!("broadcasting the game state on {}", address);
+    Some(Broadcast {
+        listener,
+        spectators: Vec::new(),
+        tick: 0,
+    })
+}
+
+fn accept_spectators(mut broadcast: ResMut<Broadcast>) {
+    loop {
+        match broadcast.listener.accept() {
+            Ok((stream, address)) => {
+                if let Err(error) = stream.set_nonblocking(true) {
+                    warn!("could not set up the spectator {}: {}", address, error);
+                    continue;
+                }
+                // the frames are small and sent one by one, they shouldn't wait for more
+                let _ = stream.set_nodelay(true);
+                info!("spectator connected from {}", address);
+                broadcast.spectators.push(Spectator {
+                    stream,
+                    address,
+                    pending: Vec::new(),
+                });
+            }
+            Err(error) => {
+                if error.kind() != ErrorKind::WouldBlock {
+                    warn!("could not accept a spectator: {}", error);
+                }
+                break;
+            }
+        }
+    }
+}
+
+// writes as much of the pending bytes as the socket takes, false once the spectator is gone
+fn flush(spectator: &mut Spectator) -> bool {
+    while !spectator.pending.is_empty() {
+        match spectator.stream.write(&spectator.pending) {
+            Ok(0) => return false,
+            Ok(written) => {
+                spectator.pending.drain(..written);
+            }
+            Err(error) if error.kind() == ErrorKind::WouldBlock => return true,
+            Err(error) if error.kind() == ErrorKind::Interrupted => {}
+            Err(_) => return false,
+        }
+    }
+    true
+}
+
+fn broadcast_state(
+    mut broadcast: ResMut<Broadcast>,
+    game_state: Res<GameState>,
+    ball_query: Query<(&Transform, &LinearVelocity, &BallState)>,
+    rectangle_query: Query<(&Transform, &PlayerRectangleState)>,
+    brick_query: Query<(&Transform, &BrickState)>,
+) {
+    broadcast.tick += 1;
+    if broadcast.spectators.is_empty() {
+        return;
+    }
+    let snapshot = Snapshot {
+        tick: broadcast.tick,
+        mode: game_state.mode,
+        in_game: game_state.in_game,
+        demo: game_state.demo,
+        paused: game_state.paused,
+        online: game_state.online,
+        level: game_state.level,
+        score: game_state.score,
+        lives: game_state.lives,
+        player_scores: game_state.player_scores,
+        player_lives: game_state.player_lives,
+        elapsed: game_state.elapsed,
+        time_left: game_state.time_left,
+        balls: ball_query
+            .iter()
+            .map(|(transform, linear_velocity, ball_state)| BallSnapshot {
+                x: transform.translation.x,
+                y: transform.translation.y,
+                velocity_x: linear_velocity.x,
+                velocity_y: linear_velocity.y,
+                radius: ball_state.radius,
+                active: ball_state.active,
+            })
+            .collect(),
+        paddles: rectangle_query
+            .iter()
+            .map(|(transform, state)| PaddleSnapshot {
+                player: state.player,
+                x: transform.translation.x,
+                y: transform.translation.y,
+                width: state.width,
+                height: state.height,
+            })
+            .collect(),
+        bricks: brick_query
+            .iter()
+            .map(|(transform, state)| BrickSnapshot {
+                x: transform.translation.x,
+                y: transform.translation.y,
+                width: state.width,
+                height: state.height,
+                hit_bar: state.hit_bar,
+                max_hit_bar: state.max_hit_bar,
+            })
+            .collect(),
+    };
+    let mut line = match serde_json::to_vec(&snapshot) {
+        Ok(line) => line,
+        Err(error) => {
+            warn!("could not serialize the game state: {}", error);
+            return;
+        }
+    };
+    line.push(b'\n');
+    broadcast.spectators.retain_mut(|spectator| {
+        if spectator.pending.len() > MAX_PENDING_BYTES {
+            warn!("spectator {} fell too far behind, disconnecting", spectator.address);
+            return false;
+        }
+        spectator.pending.extend_from_slice(&line);
+        let connected = flush(spectator);
+        if !connected {
+            info!("spectator {} disconnected", spectator.address);
+        }
+        connected
+    });
+}
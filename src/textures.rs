@@ -0,0 +1,158 @@
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::Deserialize;
+use std::fs;
+
+use crate::create_objects::{BallState, BrickState, BrickVisual, PlayerRectangleState};
+
+// optional texture manifest, without it everything is drawn with plain colors
+const TEXTURES_PATH: &str = "textures.ron";
+
+pub struct TexturesPlugin;
+
+#[derive(Deserialize)]
+struct BrickTextureEntry {
+    // bricks created with this many hits
+    max_hit_bar: i32,
+    // damage stage, the number of hits left
+    hit_bar: i32,
+    path: String,
+}
+
+// asset paths, e.g. "embedded://textures/ball.png" or a file in the assets folder;
+// textures are tinted with the theme colors, so light or grayscale images work best
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct TextureManifest {
+    ball: Option<String>,
+    paddle: Option<String>,
+    bricks: Vec<BrickTextureEntry>,
+}
+
+struct TextureSlot {
+    handle: Handle<Image>,
+    path: String,
+    // only loaded textures are used, a material with a missing texture is not drawn at all
+    ready: bool,
+    failed: bool,
+}
+
+impl TextureSlot {
+    fn usable(&self) -> Option<Handle<Image>> {
+        self.ready.then(|| self.handle.clone())
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Textures {
+    ball: Option<TextureSlot>,
+    paddle: Option<TextureSlot>,
+    bricks: HashMap<(i32, i32), TextureSlot>,
+}
+
+impl Textures {
+    pub fn ball(&self) -> Option<Handle<Image>> {
+        self.ball.as_ref().and_then(TextureSlot::usable)
+    }
+
+    pub fn paddle(&self) -> Option<Handle<Image>> {
+        self.paddle.as_ref().and_then(TextureSlot::usable)
+    }
+
+    pub fn brick(&self, max_hit_bar: i32, hit_bar: i32) -> Option<Handle<Image>> {
+        self.bricks.get(&(max_hit_bar, hit_bar)).and_then(TextureSlot::usable)
+    }
+
+    fn slots_mut(&mut self) -> impl Iterator<Item = &mut TextureSlot> {
+        self.ball
+            .iter_mut()
+            .chain(self.paddle.iter_mut())
+            .chain(self.bricks.values_mut())
+    }
+}
+
+impl Plugin for TexturesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Textures>()
+            .add_systems(Startup, setup)
+            .add_systems(Update, (track_texture_loading, apply_textures).chain());
+    }
+}
+
+fn load_manifest() -> TextureManifest {
+    match fs::read_to_string(TEXTURES_PATH) {
+        Ok(contents) => ron::from_str(&contents).unwrap_or_else(|error| {
+            warn!("invalid {}, using plain colors: {}", TEXTURES_PATH, error);
+            TextureManifest::default()
+        }),
+        Err(_) => TextureManifest::default(),
+    }
+}
+
+fn setup(asset_server: Res<AssetServer>, mut textures: ResMut<Textures>) {
+    let manifest = load_manifest();
+    let slot = |path: String| TextureSlot {
+        handle: asset_server.load(path.clone()),
+        path,
+        ready: false,
+        failed: false,
+    };
+    textures.ball = manifest.ball.map(slot);
+    textures.paddle = manifest.paddle.map(slot);
+    for entry in manifest.bricks {
+        textures.bricks.insert((entry.max_hit_bar, entry.hit_bar), slot(entry.path));
+    }
+}
+
+fn track_texture_loading(asset_server: Res<AssetServer>, mut textures: ResMut<Textures>) {
+    // avoid triggering change detection every frame once everything has settled
+    if textures.bypass_change_detection().slots_mut().all(|slot| slot.ready || slot.failed) {
+        return;
+    }
+    for slot in textures.slots_mut() {
+        if slot.ready || slot.failed {
+            continue;
+        }
+        match asset_server.get_load_state(&slot.handle) {
+            Some(LoadState::Loaded) => slot.ready = true,
+            Some(LoadState::Failed) => {
+                warn!("could not load texture {}, using plain colors", slot.path);
+                slot.failed = true;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn apply_textures(
+    textures: Res<Textures>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    ball_query: Query<Ref<Handle<ColorMaterial>>, With<BallState>>,
+    paddle_query: Query<Ref<Handle<ColorMaterial>>, With<PlayerRectangleState>>,
+    brick_query: Query<(Ref<Handle<ColorMaterial>>, &Parent), With<BrickVisual>>,
+    brick_state_query: Query<&BrickState>,
+) {
+    let mut apply = |material: &Ref<Handle<ColorMaterial>>, texture: Option<Handle<Image>>| {
+        // new materials (e.g. a brick that was hit) need their texture too
+        if !(textures.is_changed() || material.is_changed()) {
+            return;
+        }
+        if let Some(material) = materials.get_mut(&**material) {
+            if material.texture != texture {
+                material.texture = texture;
+            }
+        }
+    };
+    for material in &ball_query {
+        apply(&material, textures.ball());
+    }
+    for material in &paddle_query {
+        apply(&material, textures.paddle());
+    }
+    for (material, parent) in &brick_query {
+        if let Ok(brick_state) = brick_state_query.get(parent.get()) {
+            apply(&material, textures.brick(brick_state.max_hit_bar, brick_state.hit_bar));
+        }
+    }
+}
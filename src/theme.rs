@@ -0,0 +1,302 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::create_objects::{BallState, BrickState, BrickVisual, PlayerRectangleState};
+use crate::trail::BallTrail;
+
+// extra themes on top of the built-in ones, same format as a list of Theme
+const THEMES_PATH: &str = "themes.ron";
+
+pub struct ThemePlugin;
+
+// colors are written as hex strings, e.g. "#ff0000" or "#1a1a1af9"
+mod hex_color {
+    use bevy::prelude::Color;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    fn to_hex(color: &Color) -> String {
+        let [r, g, b, a] = color.as_rgba_u8();
+        format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+    }
+
+    fn from_hex<E: Error>(hex: &str) -> Result<Color, E> {
+        Color::hex(hex).map_err(|error| E::custom(format!("{}: {}", hex, error)))
+    }
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(color))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        from_hex(&String::deserialize(deserializer)?)
+    }
+
+    pub mod list {
+        use bevy::prelude::Color;
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(colors: &[Color], serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(colors.iter().map(super::to_hex))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Color>, D::Error> {
+            Vec::<String>::deserialize(deserializer)?
+                .iter()
+                .map(|hex| super::from_hex(hex))
+                .collect()
+        }
+    }
+}
+
+#[derive(Resource, Clone, Serialize, Deserialize)]
+pub struct Theme {
+    pub name: String,
+    #[serde(with = "hex_color")]
+    pub ball: Color,
+    #[serde(with = "hex_color")]
+    pub paddle: Color,
+    // indexed by remaining hits - 1, the last entry is used for tougher bricks
+    #[serde(with = "hex_color::list")]
+    pub bricks: Vec<Color>,
+    #[serde(with = "hex_color")]
+    pub background: Color,
+    #[serde(with = "hex_color")]
+    pub panel: Color,
+    #[serde(with = "hex_color")]
+    pub text: Color,
+    #[serde(with = "hex_color")]
+    pub button: Color,
+    #[serde(with = "hex_color")]
+    pub button_hovered: Color,
+    #[serde(with = "hex_color")]
+    pub button_pressed: Color,
+    #[serde(with = "hex_color")]
+    pub button_border: Color,
+    #[serde(with = "hex_color")]
+    pub button_border_hovered: Color,
+    #[serde(with = "hex_color")]
+    pub button_border_pressed: Color,
+}
+
+impl Theme {
+    pub fn brick(&self, hit_bar: i32) -> Color {
+        let index = (hit_bar.max(1) as usize - 1).min(self.bricks.len().saturating_sub(1));
+        self.bricks.get(index).copied().unwrap_or(Color::WHITE)
+    }
+
+    pub fn classic() -> Self {
+        Theme {
+            name: "classic".to_string(),
+            ball: Color::rgb(1.0, 0.0, 0.0),
+            paddle: Color::rgb(0.0, 0.0, 1.0),
+            bricks: vec![
+                Color::rgb(0.0, 1.0, 0.0),
+                Color::rgb(0.0, 1.0 / 2.0, 0.0),
+                Color::rgb(0.0, 1.0 / 3.0, 0.0),
+            ],
+            background: ClearColor::default().0,
+            panel: Color::rgb(0.1, 0.1, 0.1),
+            text: Color::rgb(0.9, 0.9, 0.9),
+            button: Color::rgb(0.15, 0.15, 0.15),
+            button_hovered: Color::rgb(0.25, 0.25, 0.25),
+            button_pressed: Color::rgb(0.35, 0.75, 0.35),
+            button_border: Color::BLACK,
+            button_border_hovered: Color::WHITE,
+            button_border_pressed: Color::RED,
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Theme {
+            name: "high contrast".to_string(),
+            ball: Color::WHITE,
+            paddle: Color::YELLOW,
+            bricks: vec![
+                Color::rgb(0.0, 1.0, 1.0),
+                Color::rgb(1.0, 0.0, 1.0),
+                Color::rgb(1.0, 1.0, 0.0),
+            ],
+            background: Color::BLACK,
+            panel: Color::BLACK,
+            text: Color::WHITE,
+            button: Color::BLACK,
+            button_hovered: Color::rgb(0.3, 0.3, 0.3),
+            button_pressed: Color::YELLOW,
+            button_border: Color::WHITE,
+            button_border_hovered: Color::YELLOW,
+            button_border_pressed: Color::YELLOW,
+        }
+    }
+
+    pub fn midnight() -> Self {
+        Theme {
+            name: "midnight".to_string(),
+            ball: Color::rgb(1.0, 0.8, 0.4),
+            paddle: Color::rgb(0.6, 0.7, 1.0),
+            bricks: vec![
+                Color::rgb(0.45, 0.35, 0.9),
+                Color::rgb(0.3, 0.2, 0.7),
+                Color::rgb(0.2, 0.1, 0.5),
+            ],
+            background: Color::rgb(0.03, 0.03, 0.1),
+            panel: Color::rgb(0.05, 0.05, 0.15),
+            text: Color::rgb(0.85, 0.85, 1.0),
+            button: Color::rgb(0.1, 0.1, 0.25),
+            button_hovered: Color::rgb(0.2, 0.2, 0.4),
+            button_pressed: Color::rgb(0.45, 0.35, 0.9),
+            button_border: Color::rgb(0.2, 0.2, 0.4),
+            button_border_hovered: Color::rgb(0.85, 0.85, 1.0),
+            button_border_pressed: Color::rgb(1.0, 0.8, 0.4),
+        }
+    }
+}
+
+// classic look with brick tiers picked from the Okabe-Ito palette, which stays
+// distinguishable for the common forms of color blindness
+fn colorblind(name: &str, bricks: [Color; 3]) -> Theme {
+    Theme {
+        name: name.to_string(),
+        ball: Color::WHITE,
+        bricks: bricks.to_vec(),
+        ..Theme::classic()
+    }
+}
+
+impl Theme {
+    pub fn deuteranopia() -> Self {
+        colorblind(
+            "deuteranopia",
+            [Color::rgb_u8(86, 180, 233), Color::rgb_u8(230, 159, 0), Color::rgb_u8(0, 114, 178)],
+        )
+    }
+
+    pub fn protanopia() -> Self {
+        colorblind(
+            "protanopia",
+            [Color::rgb_u8(240, 228, 66), Color::rgb_u8(86, 180, 233), Color::rgb_u8(0, 114, 178)],
+        )
+    }
+
+    pub fn tritanopia() -> Self {
+        colorblind(
+            "tritanopia",
+            [Color::rgb_u8(0, 158, 115), Color::rgb_u8(204, 121, 167), Color::rgb_u8(213, 94, 0)],
+        )
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::classic()
+    }
+}
+
+// all selectable themes, the built-in ones first
+#[derive(Resource)]
+pub struct Themes(pub Vec<Theme>);
+
+impl Themes {
+    pub fn get(&self, name: &str) -> Option<&Theme> {
+        self.0.iter().find(|theme| theme.name == name)
+    }
+
+    pub fn next_name(&self, name: &str) -> String {
+        let index = self.0.iter().position(|theme| theme.name == name).unwrap_or(0);
+        self.0[(index + 1) % self.0.len()].name.clone()
+    }
+}
+
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemeSettings {
+    pub name: String,
+}
+
+impl Default for ThemeSettings {
+    fn default() -> Self {
+        ThemeSettings {
+            name: Theme::classic().name,
+        }
+    }
+}
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_themes())
+            .init_resource::<Theme>()
+            .init_resource::<ThemeSettings>()
+            .add_systems(Update, (select_theme, apply_theme).chain());
+    }
+}
+
+fn load_themes() -> Themes {
+    let mut themes = vec![
+        Theme::classic(),
+        Theme::high_contrast(),
+        Theme::midnight(),
+        Theme::deuteranopia(),
+        Theme::protanopia(),
+        Theme::tritanopia(),
+    ];
+    if let Ok(contents) = fs::read_to_string(THEMES_PATH) {
+        match ron::from_str::<Vec<Theme>>(&contents) {
+            Ok(custom) => {
+                for theme in custom {
+                    if theme.bricks.is_empty() {
+                        warn!("theme {} in {} has no brick colors, skipping it", theme.name, THEMES_PATH);
+                    } else if themes.iter().any(|existing| existing.name == theme.name) {
+                        warn!("theme {} in {} already exists, skipping it", theme.name, THEMES_PATH);
+                    } else {
+                        themes.push(theme);
+                    }
+                }
+            }
+            Err(error) => warn!("invalid {}, using the built-in themes: {}", THEMES_PATH, error),
+        }
+    }
+    Themes(themes)
+}
+
+fn select_theme(theme_settings: Res<ThemeSettings>, themes: Res<Themes>, mut theme: ResMut<Theme>) {
+    if theme_settings.is_changed() && theme.name != theme_settings.name {
+        match themes.get(&theme_settings.name) {
+            Some(selected) => *theme = selected.clone(),
+            None => warn!("unknown theme {}, keeping {}", theme_settings.name, theme.name),
+        }
+    }
+}
+
+fn apply_theme(
+    theme: Res<Theme>,
+    mut clear_color: ResMut<ClearColor>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut ball_query: Query<(&Handle<ColorMaterial>, &mut BallTrail), With<BallState>>,
+    paddle_query: Query<&Handle<ColorMaterial>, With<PlayerRectangleState>>,
+    brick_query: Query<(&Handle<ColorMaterial>, &Parent), With<BrickVisual>>,
+    brick_state_query: Query<&BrickState>,
+) {
+    if !theme.is_changed() {
+        return;
+    }
+    clear_color.0 = theme.background;
+    for (material, mut trail) in &mut ball_query {
+        if let Some(material) = materials.get_mut(material) {
+            material.color = theme.ball;
+        }
+        trail.color = theme.ball;
+    }
+    for material in &paddle_query {
+        if let Some(material) = materials.get_mut(material) {
+            material.color = theme.paddle;
+        }
+    }
+    for (material, parent) in &brick_query {
+        let Ok(brick_state) = brick_state_query.get(parent.get()) else {
+            continue;
+        };
+        if let Some(material) = materials.get_mut(material) {
+            material.color = theme.brick(brick_state.hit_bar);
+        }
+    }
+}
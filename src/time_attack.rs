@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+
+use crate::game_logic::{BrickDestroyedEvent, GameMode, GameOverEvent, GameState};
+
+// clear the level before the countdown runs out
+pub struct TimeAttackPlugin;
+
+pub const TIME_ATTACK_START_SECS: f32 = 30.0;
+const BONUS_SECS_PER_BRICK: f32 = 2.0;
+const SCORE_PER_SECOND_LEFT: f32 = 50.0;
+
+// added to the score when the level is cleared
+pub fn time_bonus(time_left: f32) -> u32 {
+    (time_left.max(0.0) * SCORE_PER_SECOND_LEFT) as u32
+}
+
+impl Plugin for TimeAttackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (award_bonus_time, count_down).chain());
+    }
+}
+
+fn award_bonus_time(mut brick_destroyed_event: EventReader<BrickDestroyedEvent>, mut game_state: ResMut<GameState>) {
+    let destroyed = brick_destroyed_event.read().count();
+    if destroyed > 0 && game_state.mode == GameMode::TimeAttack && game_state.in_game {
+        game_state.time_left += destroyed as f32 * BONUS_SECS_PER_BRICK;
+    }
+}
+
+fn count_down(
+    mut game_state: ResMut<GameState>,
+    mut game_over_event: EventWriter<GameOverEvent>,
+    time: Res<Time>,
+) {
+    if game_state.mode != GameMode::TimeAttack || !game_state.in_game || game_state.paused {
+        return;
+    }
+    game_state.time_left = (game_state.time_left - time.delta_seconds()).max(0.0);
+    if game_state.time_left == 0.0 {
+        // leaving in_game makes sure the event is only sent once per game
+        game_state.in_game = false;
+        game_over_event.send(GameOverEvent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_bonus_pays_per_second_left() {
+        assert_eq!(time_bonus(0.0), 0);
+        assert_eq!(time_bonus(10.0), 500);
+        assert_eq!(time_bonus(1.5), 75);
+        assert!(time_bonus(TIME_ATTACK_START_SECS) > time_bonus(TIME_ATTACK_START_SECS - 1.0));
+    }
+
+    #[test]
+    fn time_bonus_is_never_negative() {
+        assert_eq!(time_bonus(-3.0), 0);
+    }
+}
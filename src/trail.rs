@@ -0,0 +1,124 @@
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::create_objects::BallState;
+
+pub struct TrailPlugin;
+
+pub const MAX_TRAIL_LENGTH: usize = 32;
+// opacity of the segment right behind the ball
+const TRAIL_ALPHA: f32 = 0.5;
+
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrailSettings {
+    // number of recent positions drawn behind each ball, 0 disables the trail
+    pub length: usize,
+}
+
+impl Default for TrailSettings {
+    fn default() -> Self {
+        TrailSettings { length: 12 }
+    }
+}
+
+#[derive(Component)]
+pub struct BallTrail {
+    // power-ups (e.g. a fireball) recolor the trail by changing this
+    pub color: Color,
+    positions: VecDeque<Vec2>,
+}
+
+impl BallTrail {
+    pub fn new(color: Color) -> Self {
+        BallTrail {
+            color,
+            positions: VecDeque::with_capacity(MAX_TRAIL_LENGTH),
+        }
+    }
+}
+
+#[derive(Component)]
+struct TrailSegment {
+    ball: Entity,
+    index: usize,
+}
+
+impl Plugin for TrailPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (record_trail, spawn_trail_segments, draw_trail).chain())
+            .init_resource::<TrailSettings>();
+    }
+}
+
+fn record_trail(
+    mut ball_query: Query<(&GlobalTransform, &BallState, &mut BallTrail)>,
+    trail_settings: Res<TrailSettings>,
+) {
+    let length = trail_settings.length.min(MAX_TRAIL_LENGTH);
+    for (global_transform, ball_state, mut trail) in &mut ball_query {
+        // a ball resting on the paddle leaves no trail
+        if !ball_state.active {
+            trail.positions.clear();
+            continue;
+        }
+        trail.positions.push_front(global_transform.translation().truncate());
+        trail.positions.truncate(length);
+    }
+}
+
+fn spawn_trail_segments(
+    mut commands: Commands,
+    ball_query: Query<(Entity, &BallState, &BallTrail), Added<BallTrail>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (ball, ball_state, trail) in &ball_query {
+        let mesh = Mesh2dHandle(meshes.add(Circle {
+            radius: ball_state.radius,
+        }));
+        for index in 0..MAX_TRAIL_LENGTH {
+            commands.spawn((
+                MaterialMesh2dBundle {
+                    mesh: mesh.clone(),
+                    // each segment fades on its own, so it needs its own material
+                    material: materials.add(trail.color),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                TrailSegment { ball, index },
+            ));
+        }
+    }
+}
+
+fn draw_trail(
+    mut commands: Commands,
+    ball_query: Query<&BallTrail>,
+    mut segment_query: Query<(Entity, &TrailSegment, &mut Transform, &mut Visibility, &Handle<ColorMaterial>)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (entity, segment, mut transform, mut visibility, material) in &mut segment_query {
+        let Ok(trail) = ball_query.get(segment.ball) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        let Some(position) = trail.positions.get(segment.index) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        // 1 right behind the ball, approaching 0 at the end of the trail
+        let remaining = 1.0 - segment.index as f32 / trail.positions.len() as f32;
+        *visibility = Visibility::Visible;
+        // just below the ball
+        transform.translation = position.extend(-0.1);
+        transform.scale = Vec3::splat(0.3 + 0.6 * remaining);
+        if let Some(material) = materials.get_mut(material) {
+            material.color = trail.color.with_a(TRAIL_ALPHA * remaining);
+        }
+    }
+}
@@ -1,18 +1,40 @@
 use bevy::prelude::*;
 
-use crate::game_logic::{GameOverEvent, GameState, GameWonEvent, HideContainersEvent};
+use bevy::ui::FocusPolicy;
 
-#[derive(Event)]
-pub struct NewGameEvent;
+use crate::editor::OpenEditorEvent;
+use crate::display::TEXT_SCALE_RANGE;
+use crate::game_logic::{
+    Control, ControlSettings, GameMode, GameOverEvent, GameRules, GameState, GameWonEvent, HideContainersEvent, StartDemoEvent,
+    BALL_SPEED_RANGE, LIVES_RANGE,
+};
+use crate::high_scores::format_time;
+use crate::level_generator::{pick_seed, GENERATED_LEVELS};
+use crate::particles::ParticleLevel;
+use crate::save_game::ContinueGameEvent;
+use crate::settings::{SettingsProblems, SettingsResources};
+use crate::theme::{Theme, Themes};
+use crate::trail::MAX_TRAIL_LENGTH;
+use crate::versus::versus_winner;
 
+#[derive(Event, Default)]
+pub struct NewGameEvent {
+    // plays a generated level instead of the loaded one
+    pub seed: Option<u32>,
+    pub mode: GameMode,
+    // set for an online guest, who plays by the host's rules
+    pub rules: Option<GameRules>,
+}
+
+const SETTINGS_FONT_SIZE: f32 = 28.0;
 
-const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
-const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
-const PRESSED_BUTTON: Color = Color::rgb(0.35, 0.75, 0.35);
 
 #[derive(Component)]
 struct Container;
 
+#[derive(Component)]
+pub struct StartContainer;
+
 #[derive(Component)]
 pub struct GameOverContainer;
 
@@ -22,22 +44,84 @@ pub struct GameWonContainer;
 #[derive(Component)]
 pub struct LivesCounter;
 
+#[derive(Component)]
+pub struct SettingsContainer;
+
+// filled in by high_scores with the table of the mode that was played
+#[derive(Component)]
+pub struct HighScoreLabel;
+
+// names the winner of a versus game
+#[derive(Component)]
+struct GameWonTitle;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SettingsValue {
+    MasterVolume,
+    SfxVolume,
+    MusicVolume,
+    Muted,
+    Particles,
+    ScreenEffects,
+    TrailLength,
+    Theme,
+    BrickNumbers,
+    BrickCracks,
+    CoopLives,
+    BallSpeed,
+    Lives,
+    Fullscreen,
+    Vsync,
+    TextScale,
+    Key(Control),
+}
+
+#[derive(Component, Clone, Copy)]
+pub enum ButtonAction {
+    NewGame,
+    Continue,
+    RandomLevel,
+    Endless,
+    TimeAttack,
+    Coop,
+    Versus,
+    OpenEditor,
+    OpenSettings,
+    CloseSettings,
+    ShowSettingsPage(usize),
+    Adjust(SettingsValue, f32),
+    Toggle(SettingsValue),
+}
+
+// text showing the current value of a setting
+#[derive(Component)]
+pub struct SettingsLabel(SettingsValue);
+
+// one group of settings, the settings screen shows one at a time
+#[derive(Component)]
+struct SettingsPage(usize);
+
+// the control waiting for the next key press to be bound to it
+#[derive(Resource, Default)]
+struct Rebinding(Option<Control>);
+
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup)
-            .add_systems(Update, (button_system, lives_counter, hide_containers, display_game_over, display_game_won))
+            .add_systems(Update, (button_system, start_from_demo, lives_counter, rebind_key, settings_labels, apply_ui_theme, hide_containers, display_start_screen, display_game_over, display_game_won))
+            .init_resource::<Rebinding>()
             .add_event::<NewGameEvent>();
             
     }
 }
 
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>, theme: Res<Theme>, problems: Res<SettingsProblems>) {
     commands
-        // new game
+        // new game, semi-transparent so the attract mode demo shows through
         .spawn((NodeBundle {
-            background_color: BackgroundColor(Color::rgba(0.1, 0.1, 0.1, 0.99)),
+            background_color: BackgroundColor(theme.panel.with_a(0.6)),
             style: Style {
                 display: Display::Flex,
                 flex_direction: FlexDirection::Column,
@@ -48,7 +132,7 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                 ..default()
             },
             ..default()
-        }, Container))
+        }, Container, StartContainer))
         .with_children(|parent| {
             // left vertical fill (border)
             parent.spawn((
@@ -74,7 +158,7 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
             // left vertical fill (border)
             parent.spawn((
                 TextBundle::from_section(
-                    "Press the button to start the game!",
+                    "Press any key to start the game!",
                     TextStyle {
                         font: asset_server.load("embedded://fonts/AgaveNerdFont-Regular.ttf"),
                         font_size: 40.0,
@@ -93,7 +177,7 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         })
         .with_children(|parent| {
             parent
-                .spawn(ButtonBundle {
+                .spawn((ButtonBundle {
                     style: Style {
                         width: Val::Px(150.0),
                         height: Val::Px(65.0),
@@ -104,24 +188,35 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                         align_items: AlignItems::Center,
                         ..default()
                     },
-                    border_color: BorderColor(Color::BLACK),
-                    background_color: BackgroundColor(NORMAL_BUTTON),
+                    border_color: BorderColor(theme.button_border),
+                    background_color: BackgroundColor(theme.button),
                     ..default()
-                })
+                }, ButtonAction::NewGame))
                 .with_children(|parent| {
                     parent.spawn(TextBundle::from_section(
                         "Start",
                         TextStyle {
                             font: asset_server.load("embedded://fonts/AgaveNerdFont-Regular.ttf"),
                             font_size: 40.0,
-                            color: Color::rgb(0.9, 0.9, 0.9),
+                            color: theme.text,
                         },
                     ));
                 });
+        })
+        .with_children(|parent| {
+            // hidden by save_game while there is no saved game
+            spawn_button(parent, &asset_server, &theme, "Continue", ButtonAction::Continue, 40.0);
+            spawn_button(parent, &asset_server, &theme, "Random level", ButtonAction::RandomLevel, 40.0);
+            spawn_button(parent, &asset_server, &theme, "Endless", ButtonAction::Endless, 40.0);
+            spawn_button(parent, &asset_server, &theme, "Time attack", ButtonAction::TimeAttack, 40.0);
+            spawn_button(parent, &asset_server, &theme, "Co-op", ButtonAction::Coop, 40.0);
+            spawn_button(parent, &asset_server, &theme, "Versus", ButtonAction::Versus, 40.0);
+            spawn_button(parent, &asset_server, &theme, "Editor", ButtonAction::OpenEditor, 40.0);
+            spawn_button(parent, &asset_server, &theme, "Settings", ButtonAction::OpenSettings, 40.0);
         });
         // game over
         commands.spawn((NodeBundle {
-            background_color: BackgroundColor(Color::rgba(0.1, 0.1, 0.1, 0.99)),
+            background_color: BackgroundColor(theme.panel.with_a(0.99)),
             visibility: Visibility::Hidden,
             style: Style {
                 display: Display::Flex,
@@ -176,9 +271,12 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                 Label,
             ));
         })
+        .with_children(|parent| {
+            spawn_high_score_label(parent, &asset_server);
+        })
         .with_children(|parent| {
             parent
-                .spawn(ButtonBundle {
+                .spawn((ButtonBundle {
                     style: Style {
                         width: Val::Px(150.0),
                         height: Val::Px(65.0),
@@ -189,24 +287,24 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                         align_items: AlignItems::Center,
                         ..default()
                     },
-                    border_color: BorderColor(Color::BLACK),
-                    background_color: BackgroundColor(NORMAL_BUTTON),
+                    border_color: BorderColor(theme.button_border),
+                    background_color: BackgroundColor(theme.button),
                     ..default()
-                })
+                }, ButtonAction::NewGame))
                 .with_children(|parent| {
                     parent.spawn(TextBundle::from_section(
                         "Start",
                         TextStyle {
                             font: asset_server.load("embedded://fonts/AgaveNerdFont-Regular.ttf"),
                             font_size: 40.0,
-                            color: Color::rgb(0.9, 0.9, 0.9),
+                            color: theme.text,
                         },
                     ));
                 });
         });
         // game won
         commands.spawn((NodeBundle {
-            background_color: BackgroundColor(Color::rgba(0.1, 0.1, 0.1, 0.99)),
+            background_color: BackgroundColor(theme.panel.with_a(0.99)),
             visibility: Visibility::Hidden,
             style: Style {
                 display: Display::Flex,
@@ -238,6 +336,7 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                 // not button/list item text, this is necessary
                 // for accessibility to treat the text accordingly.
                 Label,
+                GameWonTitle,
             ));
         })
         .with_children(|parent| {
@@ -261,9 +360,12 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                 Label,
             ));
         })
+        .with_children(|parent| {
+            spawn_high_score_label(parent, &asset_server);
+        })
         .with_children(|parent| {
             parent
-                .spawn(ButtonBundle {
+                .spawn((ButtonBundle {
                     style: Style {
                         width: Val::Px(150.0),
                         height: Val::Px(65.0),
@@ -274,21 +376,122 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                         align_items: AlignItems::Center,
                         ..default()
                     },
-                    border_color: BorderColor(Color::BLACK),
-                    background_color: BackgroundColor(NORMAL_BUTTON),
+                    border_color: BorderColor(theme.button_border),
+                    background_color: BackgroundColor(theme.button),
                     ..default()
-                })
+                }, ButtonAction::NewGame))
                 .with_children(|parent| {
                     parent.spawn(TextBundle::from_section(
                         "Start",
                         TextStyle {
                             font: asset_server.load("embedded://fonts/AgaveNerdFont-Regular.ttf"),
                             font_size: 40.0,
-                            color: Color::rgb(0.9, 0.9, 0.9),
+                            color: theme.text,
                         },
                     ));
                 });
         });
+        // settings, spawned after the other screens so it is drawn on top of them
+        commands.spawn((NodeBundle {
+            background_color: BackgroundColor(theme.panel.with_a(0.99)),
+            visibility: Visibility::Hidden,
+            // keep clicks from reaching the start screen below
+            focus_policy: FocusPolicy::Block,
+            style: Style {
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            ..default()
+        }, Container, SettingsContainer))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "Settings",
+                    TextStyle {
+                        font: asset_server.load("embedded://fonts/AgaveNerdFont-Regular.ttf"),
+                        font_size: 60.0,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(5.)),
+                    ..default()
+                }),
+                Label,
+            ));
+            // what was wrong with settings.ron, the defaults are used instead
+            if !problems.0.is_empty() {
+                parent.spawn((
+                    TextBundle::from_section(
+                        format!("settings.ron: {}", problems.0.join("\n")),
+                        TextStyle {
+                            font: asset_server.load("embedded://fonts/AgaveNerdFont-Regular.ttf"),
+                            font_size: 20.0,
+                            ..default()
+                        },
+                    )
+                    .with_style(Style {
+                        margin: UiRect::all(Val::Px(5.)),
+                        ..default()
+                    }),
+                    Label,
+                ));
+            }
+            let controls: Vec<(SettingsValue, f32)> = ControlSettings::CONTROLS
+                .iter()
+                .map(|control| (SettingsValue::Key(*control), 0.0))
+                .collect();
+            let pages: [(&str, &[(SettingsValue, f32)]); 5] = [
+                ("Audio", &[
+                    (SettingsValue::MasterVolume, 0.1),
+                    (SettingsValue::SfxVolume, 0.1),
+                    (SettingsValue::MusicVolume, 0.1),
+                    (SettingsValue::Muted, 0.0),
+                ]),
+                ("Display", &[
+                    (SettingsValue::Theme, 0.0),
+                    (SettingsValue::Particles, 0.0),
+                    (SettingsValue::TrailLength, 4.0),
+                    (SettingsValue::Fullscreen, 0.0),
+                    (SettingsValue::Vsync, 0.0),
+                ]),
+                ("Accessibility", &[
+                    (SettingsValue::TextScale, 0.25),
+                    (SettingsValue::ScreenEffects, 0.0),
+                    (SettingsValue::BrickNumbers, 0.0),
+                    (SettingsValue::BrickCracks, 0.0),
+                ]),
+                ("Difficulty", &[
+                    (SettingsValue::BallSpeed, 100.0),
+                    (SettingsValue::Lives, 1.0),
+                    (SettingsValue::CoopLives, 0.0),
+                ]),
+                ("Controls", &controls),
+            ];
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        display: Display::Flex,
+                        flex_direction: FlexDirection::Row,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (index, (title, _)) in pages.iter().enumerate() {
+                        spawn_button(parent, &asset_server, &theme, title, ButtonAction::ShowSettingsPage(index), SETTINGS_FONT_SIZE);
+                    }
+                });
+            for (index, (_, rows)) in pages.iter().enumerate() {
+                spawn_settings_column(parent, &asset_server, &theme, index, rows);
+            }
+            spawn_button(parent, &asset_server, &theme, "Back", ButtonAction::CloseSettings, 40.0);
+        });
         // lives counter
        commands.spawn((
             TextBundle::from_section(
@@ -310,36 +513,444 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         ));
 }
 
+fn spawn_button(
+    parent: &mut ChildBuilder,
+    asset_server: &AssetServer,
+    theme: &Theme,
+    label: &str,
+    action: ButtonAction,
+    font_size: f32,
+) {
+    parent
+        .spawn((ButtonBundle {
+            style: Style {
+                min_width: Val::Px(font_size * 1.5),
+                height: Val::Px(font_size * 1.6),
+                border: UiRect::all(Val::Px(5.0)),
+                margin: UiRect::all(Val::Px(5.0)),
+                padding: UiRect::horizontal(Val::Px(10.0)),
+                // horizontally center child text
+                justify_content: JustifyContent::Center,
+                // vertically center child text
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            border_color: BorderColor(theme.button_border),
+            background_color: BackgroundColor(theme.button),
+            ..default()
+        }, action))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font: asset_server.load("embedded://fonts/AgaveNerdFont-Regular.ttf"),
+                    font_size,
+                    color: theme.text,
+                },
+            ));
+        });
+}
+
+fn spawn_high_score_label(parent: &mut ChildBuilder, asset_server: &AssetServer) {
+    parent.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                // monospace digits keep the table columns aligned
+                font: asset_server.load("embedded://fonts/AgaveNerdFont-Regular.ttf"),
+                font_size: 24.0,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            margin: UiRect::all(Val::Px(5.)),
+            ..default()
+        }),
+        Label,
+        HighScoreLabel,
+    ));
+}
+
+fn spawn_settings_column(
+    parent: &mut ChildBuilder,
+    asset_server: &AssetServer,
+    theme: &Theme,
+    page: usize,
+    rows: &[(SettingsValue, f32)],
+) {
+    parent
+        .spawn((NodeBundle {
+            style: Style {
+                // the first page shows when the settings are opened
+                display: if page == 0 { Display::Flex } else { Display::None },
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::FlexEnd,
+                margin: UiRect::all(Val::Px(20.0)),
+                ..default()
+            },
+            ..default()
+        }, SettingsPage(page)))
+        .with_children(|parent| {
+            for (value, step) in rows {
+                spawn_settings_row(parent, asset_server, theme, *value, *step);
+            }
+        });
+}
+
+// a "- value +" row, or a single toggle button when step is 0
+fn spawn_settings_row(parent: &mut ChildBuilder, asset_server: &AssetServer, theme: &Theme, value: SettingsValue, step: f32) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                display: Display::Flex,
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            if step != 0.0 {
+                spawn_button(parent, asset_server, theme, "-", ButtonAction::Adjust(value, -step), SETTINGS_FONT_SIZE);
+            }
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("embedded://fonts/AgaveNerdFont-Regular.ttf"),
+                        font_size: SETTINGS_FONT_SIZE,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(5.)),
+                    min_width: Val::Px(SETTINGS_FONT_SIZE * 11.0),
+                    ..default()
+                }),
+                Label,
+                SettingsLabel(value),
+            ));
+            if step != 0.0 {
+                spawn_button(parent, asset_server, theme, "+", ButtonAction::Adjust(value, step), SETTINGS_FONT_SIZE);
+            } else if matches!(value, SettingsValue::Key(_)) {
+                spawn_button(parent, asset_server, theme, "Change", ButtonAction::Toggle(value), SETTINGS_FONT_SIZE);
+            } else {
+                spawn_button(parent, asset_server, theme, "Toggle", ButtonAction::Toggle(value), SETTINGS_FONT_SIZE);
+            }
+        });
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+fn control_name(control: Control) -> String {
+    match control {
+        Control::Left(player) => format!("P{} left", player + 1),
+        Control::Right(player) => format!("P{} right", player + 1),
+        Control::Launch => "Launch".to_string(),
+    }
+}
+
+fn settings_text(value: SettingsValue, settings: &SettingsResources, rebinding: &Rebinding) -> String {
+    let percent = |volume: f32| (volume * 100.0).round() as i32;
+    match value {
+        SettingsValue::MasterVolume => format!("Master: {}%", percent(settings.audio.master_volume)),
+        SettingsValue::SfxVolume => format!("Effects: {}%", percent(settings.audio.sfx_volume)),
+        SettingsValue::MusicVolume => format!("Music: {}%", percent(settings.audio.music_volume)),
+        SettingsValue::Muted => format!("Muted: {}", if settings.audio.muted { "yes" } else { "no" }),
+        SettingsValue::Particles => format!(
+            "Particles: {}",
+            match settings.particles.level {
+                ParticleLevel::Off => "off",
+                ParticleLevel::Reduced => "reduced",
+                ParticleLevel::Full => "full",
+            }
+        ),
+        SettingsValue::ScreenEffects => format!("Shake & hit-stop: {}", on_off(settings.screen_effects.enabled)),
+        SettingsValue::TrailLength => format!("Ball trail: {}", settings.trail.length),
+        SettingsValue::Theme => format!("Theme: {}", settings.theme.name),
+        SettingsValue::BrickNumbers => format!("Brick hit numbers: {}", on_off(settings.brick_health.numbers)),
+        SettingsValue::BrickCracks => format!("Brick cracks: {}", on_off(settings.brick_health.cracks)),
+        SettingsValue::CoopLives => format!("Co-op lives: {}", if settings.coop.shared_lives { "shared" } else { "separate" }),
+        SettingsValue::BallSpeed => format!("Ball speed: {}", settings.difficulty.ball_speed.round() as i32),
+        SettingsValue::Lives => format!("Lives: {}", settings.difficulty.lives),
+        SettingsValue::Fullscreen => format!("Fullscreen: {}", on_off(settings.display.fullscreen)),
+        SettingsValue::Vsync => format!("Vsync: {}", on_off(settings.display.vsync)),
+        SettingsValue::TextScale => format!("Text size: {}%", percent(settings.display.text_scale)),
+        SettingsValue::Key(control) if rebinding.0 == Some(control) => format!("{}: press a key", control_name(control)),
+        SettingsValue::Key(control) => format!("{}: {:?}", control_name(control), settings.controls.key(control)),
+    }
+}
+
+fn adjust_setting(value: SettingsValue, step: f32, settings: &mut SettingsResources, themes: &Themes) {
+    let volume = match value {
+        SettingsValue::MasterVolume => &mut settings.audio.master_volume,
+        SettingsValue::SfxVolume => &mut settings.audio.sfx_volume,
+        SettingsValue::MusicVolume => &mut settings.audio.music_volume,
+        SettingsValue::Muted => {
+            settings.audio.muted = !settings.audio.muted;
+            return;
+        }
+        SettingsValue::Particles => {
+            settings.particles.level = settings.particles.level.next();
+            return;
+        }
+        SettingsValue::ScreenEffects => {
+            settings.screen_effects.enabled = !settings.screen_effects.enabled;
+            return;
+        }
+        SettingsValue::TrailLength => {
+            let length = settings.trail.length as f32 + step;
+            settings.trail.length = length.clamp(0.0, MAX_TRAIL_LENGTH as f32) as usize;
+            return;
+        }
+        SettingsValue::Theme => {
+            settings.theme.name = themes.next_name(&settings.theme.name);
+            return;
+        }
+        SettingsValue::BrickNumbers => {
+            settings.brick_health.numbers = !settings.brick_health.numbers;
+            return;
+        }
+        SettingsValue::BrickCracks => {
+            settings.brick_health.cracks = !settings.brick_health.cracks;
+            return;
+        }
+        SettingsValue::CoopLives => {
+            settings.coop.shared_lives = !settings.coop.shared_lives;
+            return;
+        }
+        SettingsValue::BallSpeed => {
+            let (min, max) = BALL_SPEED_RANGE;
+            settings.difficulty.ball_speed = (settings.difficulty.ball_speed + step).clamp(min, max);
+            return;
+        }
+        SettingsValue::Lives => {
+            let (min, max) = LIVES_RANGE;
+            settings.difficulty.lives = (settings.difficulty.lives + step as i32).clamp(min, max);
+            return;
+        }
+        SettingsValue::Fullscreen => {
+            settings.display.fullscreen = !settings.display.fullscreen;
+            return;
+        }
+        SettingsValue::Vsync => {
+            settings.display.vsync = !settings.display.vsync;
+            return;
+        }
+        SettingsValue::TextScale => {
+            let (min, max) = TEXT_SCALE_RANGE;
+            let scale = settings.display.text_scale + step;
+            settings.display.text_scale = (scale.clamp(min, max) * 100.0).round() / 100.0;
+            return;
+        }
+        // bound by rebind_key with the next key press
+        SettingsValue::Key(_) => return,
+    };
+    // round to get rid of float drift from repeated steps
+    *volume = ((*volume + step).clamp(0.0, 1.0) * 100.0).round() / 100.0;
+}
+
+fn settings_labels(
+    settings: SettingsResources,
+    rebinding: Res<Rebinding>,
+    mut label_query: Query<(&mut Text, &SettingsLabel)>,
+) {
+    for (mut text, label) in &mut label_query {
+        if settings.is_changed() || rebinding.is_changed() || text.sections[0].value.is_empty() {
+            text.sections[0].value = settings_text(label.0, &settings, &rebinding);
+        }
+    }
+}
+
+// binds the next key press to the control being changed, escape keeps the old key
+fn rebind_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut controls: ResMut<ControlSettings>,
+) {
+    let Some(control) = rebinding.0 else {
+        return;
+    };
+    let Some(key) = keyboard_input.get_just_pressed().next().copied() else {
+        return;
+    };
+    rebinding.0 = None;
+    // escape cancels, the other reserved keys can't be bound either
+    if ControlSettings::RESERVED_KEYS.contains(&key) {
+        return;
+    }
+    // a control already on the key gets the old one, so no key does two things
+    let old_key = controls.key(control);
+    if let Some(other) = ControlSettings::CONTROLS.into_iter().find(|other| *other != control && controls.key(*other) == key) {
+        *controls.key_mut(other) = old_key;
+    }
+    *controls.key_mut(control) = key;
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn button_system(
     mut interaction_query: Query<
         (
             &Interaction,
+            &ButtonAction,
             &mut BackgroundColor,
             &mut BorderColor,
         ),
         (Changed<Interaction>, With<Button>),
     >,
     mut new_game_event: EventWriter<NewGameEvent>,
+    mut open_editor_event: EventWriter<OpenEditorEvent>,
+    mut continue_game_event: EventWriter<ContinueGameEvent>,
+    mut settings_query: Query<&mut Visibility, With<SettingsContainer>>,
+    mut page_query: Query<(&mut Style, &SettingsPage)>,
+    mut rebinding: ResMut<Rebinding>,
+    mut settings: SettingsResources,
+    theme: Res<Theme>,
+    themes: Res<Themes>,
+    game_state: Res<GameState>,
 ) {
-    for (interaction, mut color, mut border_color) in &mut interaction_query {
+    for (interaction, action, mut color, mut border_color) in &mut interaction_query {
         match *interaction {
             Interaction::Pressed => {
-                *color = PRESSED_BUTTON.into();
-                border_color.0 = Color::RED;
-                new_game_event.send(NewGameEvent);
+                *color = theme.button_pressed.into();
+                border_color.0 = theme.button_border_pressed;
+                match *action {
+                    ButtonAction::NewGame => {
+                        // the start screen plays the loaded level, after a game the same level again
+                        if game_state.demo {
+                            new_game_event.send(NewGameEvent::default());
+                        } else {
+                            new_game_event.send(NewGameEvent {
+                                seed: game_state.seed,
+                                mode: game_state.mode,
+                                rules: None,
+                            });
+                        }
+                    }
+                    ButtonAction::Continue => {
+                        continue_game_event.send(ContinueGameEvent);
+                    }
+                    ButtonAction::RandomLevel => {
+                        new_game_event.send(NewGameEvent {
+                            seed: Some(pick_seed()),
+                            mode: GameMode::Classic,
+                            rules: None,
+                        });
+                    }
+                    ButtonAction::Endless => {
+                        new_game_event.send(NewGameEvent {
+                            seed: Some(pick_seed()),
+                            mode: GameMode::Endless,
+                            rules: None,
+                        });
+                    }
+                    ButtonAction::TimeAttack => {
+                        new_game_event.send(NewGameEvent {
+                            seed: None,
+                            mode: GameMode::TimeAttack,
+                            rules: None,
+                        });
+                    }
+                    ButtonAction::Coop => {
+                        new_game_event.send(NewGameEvent {
+                            seed: None,
+                            mode: GameMode::Coop,
+                            rules: None,
+                        });
+                    }
+                    ButtonAction::Versus => {
+                        new_game_event.send(NewGameEvent {
+                            seed: None,
+                            mode: GameMode::Versus,
+                            rules: None,
+                        });
+                    }
+                    ButtonAction::OpenEditor => {
+                        open_editor_event.send(OpenEditorEvent);
+                    }
+                    ButtonAction::OpenSettings => {
+                        *settings_query.single_mut() = Visibility::Visible;
+                    }
+                    ButtonAction::CloseSettings => {
+                        *settings_query.single_mut() = Visibility::Hidden;
+                        rebinding.0 = None;
+                    }
+                    ButtonAction::ShowSettingsPage(index) => {
+                        for (mut style, page) in &mut page_query {
+                            style.display = if page.0 == index { Display::Flex } else { Display::None };
+                        }
+                        rebinding.0 = None;
+                    }
+                    ButtonAction::Toggle(SettingsValue::Key(control)) => rebinding.0 = Some(control),
+                    ButtonAction::Adjust(value, step) => adjust_setting(value, step, &mut settings, &themes),
+                    ButtonAction::Toggle(value) => adjust_setting(value, 0.0, &mut settings, &themes),
+                }
             }
             Interaction::Hovered => {
-                *color = HOVERED_BUTTON.into();
-                border_color.0 = Color::WHITE;
+                *color = theme.button_hovered.into();
+                border_color.0 = theme.button_border_hovered;
             }
             Interaction::None => {
-                *color = NORMAL_BUTTON.into();
-                border_color.0 = Color::BLACK;
+                *color = theme.button.into();
+                border_color.0 = theme.button_border;
             }
         }
     }
 }
 
+fn apply_ui_theme(
+    theme: Res<Theme>,
+    mut button_query: Query<(&mut BackgroundColor, &mut BorderColor), With<Button>>,
+    mut container_query: Query<&mut BackgroundColor, (With<Container>, Without<Button>)>,
+    // brick labels are Text2d and colored by brick_health
+    mut text_query: Query<&mut Text, With<Node>>,
+) {
+    if !theme.is_changed() {
+        return;
+    }
+    for (mut color, mut border_color) in &mut button_query {
+        *color = theme.button.into();
+        border_color.0 = theme.button_border;
+    }
+    for mut color in &mut container_query {
+        // keep each screen's transparency
+        color.0 = theme.panel.with_a(color.0.a());
+    }
+    for mut text in &mut text_query {
+        for section in text.sections.iter_mut() {
+            section.style.color = theme.text;
+        }
+    }
+}
+
+fn start_from_demo(
+    game_state: Res<GameState>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepad_input: Res<ButtonInput<GamepadButton>>,
+    mut new_game_event: EventWriter<NewGameEvent>,
+    settings_query: Query<&Visibility, With<SettingsContainer>>,
+) {
+    // keys pressed on the settings screen are for it, e.g. a new key for a control
+    if settings_query.iter().any(|visibility| visibility == Visibility::Visible) {
+        return;
+    }
+    // any key but the hotkeys like mute, which keep working on the start screen, and any
+    // gamepad button, mouse clicks are left to the buttons of the start screen
+    let start_pressed = keyboard_input
+        .get_just_pressed()
+        .any(|key| !ControlSettings::RESERVED_KEYS.contains(key))
+        || gamepad_input.get_just_pressed().next().is_some();
+    if game_state.demo && start_pressed {
+        new_game_event.send(NewGameEvent::default());
+    }
+}
+
 fn hide_containers(
     mut hide_containers_event: EventReader<HideContainersEvent>,
     mut visibility_query: Query<&mut Visibility, With<Container>>,
@@ -355,10 +966,69 @@ fn lives_counter(
     game_state: Res<GameState>,
     mut text_query: Query<&mut Text, With<LivesCounter>>,
 ) {
-    // update lives counter
-    if game_state.in_game {
+    // update lives counter, the demo game and the editor have no lives to show
+    if game_state.is_changed() {
         let mut text = text_query.single_mut();
-        text.sections[0].value = format!("Lives: {}", game_state.lives);
+        let value = if game_state.demo || game_state.editing {
+            String::new()
+        } else {
+            let mut value = format!("Lives: {}   Score: {}", game_state.lives, game_state.score);
+            match game_state.mode {
+                GameMode::Classic => {}
+                GameMode::Endless => value += &format!("   Time: {}", format_time(game_state.elapsed as u32)),
+                // rounded up so the game ends as the countdown shows 0:00
+                GameMode::TimeAttack => value += &format!("   Time left: {}", format_time(game_state.time_left.ceil() as u32)),
+                GameMode::Coop => {
+                    // player 2 plays on the left, so their numbers come first
+                    value = if game_state.rules.shared_lives {
+                        format!("Lives: {}", game_state.lives)
+                    } else {
+                        format!("P2 lives: {}   P1 lives: {}", game_state.player_lives[1], game_state.player_lives[0])
+                    };
+                    value += &format!(
+                        "   P2: {}   P1: {}   Score: {}",
+                        game_state.player_scores[1], game_state.player_scores[0], game_state.score
+                    );
+                }
+                // player 1 defends the bottom line, player 2 the top one
+                GameMode::Versus => {
+                    value = format!(
+                        "P1 lives: {}   P1: {}   P2 lives: {}   P2: {}",
+                        game_state.player_lives[0],
+                        game_state.player_scores[0],
+                        game_state.player_lives[1],
+                        game_state.player_scores[1]
+                    );
+                }
+            }
+            // shown so a good generated level can be shared
+            if let Some(seed) = game_state.seed {
+                if matches!(game_state.mode, GameMode::Classic | GameMode::TimeAttack | GameMode::Coop) {
+                    value += &format!("   Level: {}/{}", game_state.level, GENERATED_LEVELS);
+                }
+                value += &format!("   Seed: {}", seed);
+            }
+            if game_state.paused {
+                value += " (paused, launch to go on)";
+            }
+            value
+        };
+        // the play time changes every frame, the text only every second
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
+
+// the demo runs behind the start screen, e.g. after leaving the editor
+fn display_start_screen(
+    mut start_demo_event: EventReader<StartDemoEvent>,
+    mut visibility_query: Query<&mut Visibility, With<StartContainer>>,
+) {
+    for _ in start_demo_event.read() {
+        for mut visibility in visibility_query.iter_mut() {
+            *visibility = Visibility::Visible;
+        }
     }
 }
 
@@ -376,10 +1046,19 @@ fn display_game_over(
 fn display_game_won(
     mut game_won_event: EventReader<GameWonEvent>,
     mut visibility_query: Query<&mut Visibility, With<GameWonContainer>>,
+    mut title_query: Query<&mut Text, With<GameWonTitle>>,
+    game_state: Res<GameState>,
 ) {
     for _ in game_won_event.read() {
         for mut visibility in visibility_query.iter_mut() {
             *visibility = Visibility::Visible;
         }
+        let title = match versus_winner(game_state.player_lives) {
+            Some(winner) if game_state.mode == GameMode::Versus => format!("Player {} wins!", winner + 1),
+            _ => "Game won!".to_string(),
+        };
+        for mut text in &mut title_query {
+            text.sections[0].value = title.clone();
+        }
     }
 }
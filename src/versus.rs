@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+
+use crate::game_logic::{BallLostEvent, GameMode, GameState, GameWonEvent};
+
+// two players face each other, each loses a life when the ball crosses their goal line
+pub struct VersusPlugin;
+
+impl Plugin for VersusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, lose_versus_lives);
+    }
+}
+
+// the index of the player with lives left once the other has none
+pub fn versus_winner(player_lives: [i32; 2]) -> Option<usize> {
+    match player_lives {
+        [lives, _] if lives <= 0 => Some(1),
+        [_, lives] if lives <= 0 => Some(0),
+        _ => None,
+    }
+}
+
+fn lose_versus_lives(
+    mut ball_lost_event: EventReader<BallLostEvent>,
+    mut game_state: ResMut<GameState>,
+    mut game_won_event: EventWriter<GameWonEvent>,
+) {
+    for event in ball_lost_event.read() {
+        if game_state.mode != GameMode::Versus || !game_state.in_game {
+            continue;
+        }
+        game_state.player_lives[event.player] -= 1;
+        if versus_winner(game_state.player_lives).is_some() {
+            // leaving in_game makes sure the event is only sent once per game
+            game_state.in_game = false;
+            game_won_event.send(GameWonEvent);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versus_winner_once_a_player_is_out() {
+        assert_eq!(versus_winner([3, 3]), None);
+        assert_eq!(versus_winner([1, 2]), None);
+        assert_eq!(versus_winner([0, 2]), Some(1));
+        assert_eq!(versus_winner([2, 0]), Some(0));
+        assert_eq!(versus_winner([1, -1]), Some(0));
+    }
+}